@@ -0,0 +1,231 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::copy::RenderedFile;
+use crate::paths::is_inside;
+use crate::project::ProjectConfig;
+use crate::template::{VariableType, hash_content};
+use crate::value::Value;
+
+/// Directory in a generated project holding the files copy steps wrote, as
+/// the common ancestor when merging template updates. It ignores itself in
+/// git, so unlike the answers file it isn't committed.
+const MERGE_BASE_DIR: &str = ".newnew/base";
+
+/// The record of how a project was generated, written into the project
+/// directory after a successful run.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecordedAnswers {
+    pub template: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template_version: Option<String>,
+    #[serde(default)]
+    pub template_hash: String,
+    pub variables: BTreeMap<String, Value>,
+    /// Built-in values such as the date and uuid, so updates render files
+    /// the same way the project was generated
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub builtins: BTreeMap<String, Value>,
+    /// SHA-256 hashes of the files written by copy steps, keyed by their
+    /// path in the project, to tell which ones were modified since
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub files: BTreeMap<String, String>,
+}
+
+impl RecordedAnswers {
+    /// Record the answers of a generated project. Password variables, and
+    /// files containing their values, are left out so secrets don't end up
+    /// in the project directory.
+    pub fn new(config: &ProjectConfig, files: &[RenderedFile]) -> Self {
+        let is_password = |name: &String| config.template.variables
+            .iter()
+            .any(|var| &var.name == name && var.type_ == VariableType::Password);
+
+        RecordedAnswers {
+            template: config.template_name.clone(),
+            template_version: config.template.version.clone(),
+            template_hash: config.template.hash.clone(),
+            variables: config.variables
+                .iter()
+                .filter(|(name, _)| !is_password(name))
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+            builtins: config.builtins.clone().into_iter().collect(),
+            files: files
+                .iter()
+                .filter(|file| !contains_password(config, file))
+                .map(|file| (file.key(), hash_content(&file.content)))
+                .collect(),
+        }
+    }
+}
+
+/// Whether `file` contains the value of one of the project's password
+/// variables
+pub(crate) fn contains_password(config: &ProjectConfig, file: &RenderedFile) -> bool {
+    config.template.variables
+        .iter()
+        .filter(|var| var.type_ == VariableType::Password)
+        .filter_map(|var| match config.variables.get(&var.name) {
+            Some(Value::String(secret)) if !secret.is_empty() => Some(secret.as_bytes()),
+            _ => None,
+        })
+        .any(|secret| file.content.windows(secret.len()).any(|window| window == secret))
+}
+
+/// Keep the files copy steps wrote as the merge base for the next update,
+/// replacing the previous one. Files containing passwords are left out.
+pub(crate) fn write_merge_base(project_path: &Path, config: &ProjectConfig, files: &[RenderedFile]) -> io::Result<()> {
+    let base_dir = project_path.join(MERGE_BASE_DIR);
+    if base_dir.exists() {
+        fs::remove_dir_all(&base_dir)?;
+    }
+    fs::create_dir_all(&base_dir)?;
+    fs::write(base_dir.join(".gitignore"), "*\n")?;
+
+    for file in files.iter().filter(|file| !contains_password(config, file)) {
+        let dest = merge_base_path(project_path, &file.key());
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&dest, &file.content)
+            .map_err(|e| io::Error::new(
+                e.kind(),
+                format!("Failed to write file '{}': {}", dest.display(), e)
+            ))?;
+    }
+    Ok(())
+}
+
+/// The merge base of the file recorded as `key` with hash `hash`, or `None`
+/// if it isn't available, e.g. in a fresh clone of the project
+pub(crate) fn read_merge_base(project_path: &Path, key: &str, hash: &str) -> Option<Vec<u8>> {
+    fs::read(merge_base_path(project_path, key))
+        .ok()
+        .filter(|content| hash_content(content) == hash)
+}
+
+/// Files live in a subdirectory so a template's own `.gitignore` doesn't
+/// replace the one ignoring the merge base
+fn merge_base_path(project_path: &Path, key: &str) -> PathBuf {
+    project_path.join(MERGE_BASE_DIR).join("files").join(key)
+}
+
+/// Values read from an answers file
+#[derive(Debug, Default)]
+pub struct Answers {
+    /// Template recorded in a replay file, if any
+    pub template: Option<String>,
+    pub variables: HashMap<String, Value>,
+}
+
+/// Load variable values from a YAML, TOML or JSON answers file. The format is
+/// chosen from the file extension. Both flat `name: value` files and answers
+/// recorded by a previous run, which hold the values in a `variables`
+/// table, are accepted.
+pub fn load_answers(path: &Path) -> io::Result<Answers> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| io::Error::new(
+            e.kind(),
+            format!("Failed to read answers file '{}': {}", path.display(), e)
+        ))?;
+
+    let extension = path.extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    let invalid = |e: String| io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Failed to parse answers file '{}': {}", path.display(), e)
+    );
+
+    let document = match parse::<serde_json::Value>(&extension, &content) {
+        Ok(Some(document)) => document,
+        Ok(None) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Unsupported answers file '{}'. Use a .yml, .yaml, .toml or .json file.",
+                    path.display()
+                )
+            ));
+        },
+        Err(e) => return Err(invalid(e)),
+    };
+
+    // Recorded answers keep the values in a `variables` table. A flat file
+    // can't have one: variable values are never tables.
+    if document.get("variables").is_some_and(serde_json::Value::is_object) {
+        let recorded: RecordedAnswers = serde_json::from_value(document)
+            .map_err(|e| invalid(e.to_string()))?;
+        return Ok(Answers {
+            template: Some(recorded.template),
+            variables: recorded.variables.into_iter().collect(),
+        });
+    }
+
+    let answers: HashMap<String, Value> = serde_json::from_value(document)
+        .map_err(|e| invalid(e.to_string()))?;
+
+    Ok(Answers {
+        template: None,
+        variables: answers,
+    })
+}
+
+/// Parse `content` in the format given by `extension`, or `None` if the
+/// extension isn't a supported format.
+fn parse<T: DeserializeOwned>(extension: &str, content: &str) -> Result<Option<T>, String> {
+    match extension {
+        "yml" | "yaml" => serde_yaml::from_str(content).map(Some).map_err(|e| e.to_string()),
+        "toml" => toml::from_str(content).map(Some).map_err(|e| e.to_string()),
+        "json" => serde_json::from_str(content).map(Some).map_err(|e| e.to_string()),
+        _ => Ok(None),
+    }
+}
+
+/// Read the answers recorded in a generated project
+pub fn load_recorded(path: &Path) -> io::Result<RecordedAnswers> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| io::Error::new(
+            e.kind(),
+            format!("Failed to read answers file '{}': {}", path.display(), e)
+        ))?;
+
+    let recorded: RecordedAnswers = serde_yaml::from_str(&content)
+        .map_err(|e| io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Failed to parse answers file '{}': {}", path.display(), e)
+        ))?;
+
+    // The file is committed with the project, so its paths are untrusted
+    if let Some(key) = recorded.files.keys().find(|key| key.is_empty() || !is_inside(Path::new(key))) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid answers file '{}': file '{}' is not inside the project", path.display(), key)
+        ));
+    }
+    Ok(recorded)
+}
+
+/// Write the answers used to generate a project into `path` so the
+/// generation can be audited and replayed with `--answers`.
+pub fn write_answers(path: &Path, recorded: &RecordedAnswers) -> io::Result<()> {
+    let yaml = serde_yaml::to_string(recorded).map_err(io::Error::other)?;
+    let content = format!(
+        "# Answers used by newnew to generate this project.\n\
+         # Re-run the generation with: newnew --answers {}\n{}",
+        path.file_name().and_then(|s| s.to_str()).unwrap_or_default(),
+        yaml
+    );
+
+    fs::write(path, content)
+        .map_err(|e| io::Error::new(
+            e.kind(),
+            format!("Failed to write answers file '{}': {}", path.display(), e)
+        ))
+}
@@ -0,0 +1,58 @@
+use chrono::{Local, SecondsFormat};
+use std::collections::HashMap;
+use std::env;
+use crate::project::planned_output;
+use crate::utils::capture_command;
+use crate::value::Value;
+
+/// Variables available to every template without being declared
+pub const BUILTIN_VARIABLES: &[&str] = &[
+    "project_name",
+    "project_dir",
+    "project_name_snake",
+    "project_name_kebab",
+    "project_name_pascal",
+    "project_name_title",
+    "year",
+    "date",
+    "timestamp",
+    "user",
+    "git_user_name",
+    "git_user_email",
+    "os",
+    "arch",
+    "newnew_version",
+    "template_name",
+    "uuid",
+];
+
+/// Values describing when, where and by whom a project is generated. Taken
+/// once per run, so every file sees the same timestamp and uuid. Values that
+/// can't be determined (e.g. git isn't configured) are empty strings. When
+/// only planning, git isn't run and its values are placeholders.
+pub fn context_variables(template_name: &str, plan: bool) -> HashMap<String, Value> {
+    let now = Local::now();
+    let user = env::var("USER").or_else(|_| env::var("USERNAME")).unwrap_or_default();
+    let git_config = |key: &str| {
+        let command = format!("git config {}", key);
+        if plan {
+            planned_output(&command)
+        } else {
+            Value::from(capture_command(&command).unwrap_or_default())
+        }
+    };
+
+    HashMap::from([
+        ("year".to_string(), Value::Integer(now.format("%Y").to_string().parse().unwrap_or_default())),
+        ("date".to_string(), Value::from(now.format("%Y-%m-%d").to_string())),
+        ("timestamp".to_string(), Value::from(now.to_rfc3339_opts(SecondsFormat::Secs, false))),
+        ("user".to_string(), Value::from(user)),
+        ("git_user_name".to_string(), git_config("user.name")),
+        ("git_user_email".to_string(), git_config("user.email")),
+        ("os".to_string(), Value::from(env::consts::OS)),
+        ("arch".to_string(), Value::from(env::consts::ARCH)),
+        ("newnew_version".to_string(), Value::from(env!("CARGO_PKG_VERSION"))),
+        ("template_name".to_string(), Value::from(template_name)),
+        ("uuid".to_string(), Value::from(uuid::Uuid::new_v4().to_string())),
+    ])
+}
@@ -0,0 +1,454 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use crate::value::Value;
+
+/// A parsed `if`/`if-not` expression, such as
+/// `use_docker && (project_type == "Library" || "tokio" in dependencies)`.
+///
+/// Supports variable names, string/integer/boolean literals, `!`, `&&`,
+/// `||`, parentheses, `==`, `!=`, `<`, `<=`, `>`, `>=` and `in`. Conditions
+/// are parsed when the template is loaded, so syntax errors surface before
+/// anything is prompted for.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Condition {
+    source: String,
+    expr: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Variable(String),
+    Literal(Value),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(CompareOp, Box<Expr>, Box<Expr>),
+    In(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Condition {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let tokens = tokenize(source).map_err(|e| format!("Invalid condition '{}': {}", source, e))?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.parse_or()
+            .and_then(|expr| match parser.peek() {
+                None => Ok(expr),
+                Some(token) => Err(format!("unexpected {}", token)),
+            })
+            .map_err(|e| format!("Invalid condition '{}': {}", source, e))?;
+
+        Ok(Condition {
+            source: source.to_string(),
+            expr,
+        })
+    }
+
+    /// Whether the condition holds. Variables that aren't set (e.g. because
+    /// they were skipped) count as false, or as an empty string in
+    /// comparisons.
+    pub fn evaluate(&self, variables: &HashMap<String, Value>) -> bool {
+        evaluate(&self.expr, variables).is_truthy()
+    }
+
+    /// Names of the variables the condition refers to
+    pub fn variables(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        collect_variables(&self.expr, &mut names);
+        names
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl TryFrom<String> for Condition {
+    type Error = String;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        Condition::parse(&source)
+    }
+}
+
+impl From<Condition> for String {
+    fn from(condition: Condition) -> Self {
+        condition.source
+    }
+}
+
+fn evaluate(expr: &Expr, variables: &HashMap<String, Value>) -> Value {
+    match expr {
+        Expr::Variable(name) => variables
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| Value::String(String::new())),
+        Expr::Literal(value) => value.clone(),
+        Expr::Not(inner) => Value::Bool(!evaluate(inner, variables).is_truthy()),
+        Expr::And(left, right) => Value::Bool(
+            evaluate(left, variables).is_truthy() && evaluate(right, variables).is_truthy()
+        ),
+        Expr::Or(left, right) => Value::Bool(
+            evaluate(left, variables).is_truthy() || evaluate(right, variables).is_truthy()
+        ),
+        Expr::Compare(op, left, right) => {
+            Value::Bool(compare(*op, &evaluate(left, variables), &evaluate(right, variables)))
+        },
+        Expr::In(item, collection) => {
+            let item = evaluate(item, variables).to_string();
+            Value::Bool(match evaluate(collection, variables) {
+                Value::List(items) => items.contains(&item),
+                other => other.to_string().contains(&item),
+            })
+        },
+    }
+}
+
+/// Compare numerically when both sides are integers (or strings holding
+/// integers), otherwise by their text. Ordering only applies to integers.
+fn compare(op: CompareOp, left: &Value, right: &Value) -> bool {
+    let as_integer = |value: &Value| match value {
+        Value::Integer(i) => Some(*i),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    };
+
+    if let (Some(left), Some(right)) = (as_integer(left), as_integer(right)) {
+        return match op {
+            CompareOp::Eq => left == right,
+            CompareOp::Ne => left != right,
+            CompareOp::Lt => left < right,
+            CompareOp::Le => left <= right,
+            CompareOp::Gt => left > right,
+            CompareOp::Ge => left >= right,
+        };
+    }
+
+    match op {
+        CompareOp::Eq => left.to_string() == right.to_string(),
+        CompareOp::Ne => left.to_string() != right.to_string(),
+        _ => false,
+    }
+}
+
+fn collect_variables<'a>(expr: &'a Expr, names: &mut Vec<&'a str>) {
+    match expr {
+        Expr::Variable(name) => {
+            if !names.contains(&name.as_str()) {
+                names.push(name);
+            }
+        },
+        Expr::Literal(_) => {},
+        Expr::Not(inner) => collect_variables(inner, names),
+        Expr::And(left, right)
+        | Expr::Or(left, right)
+        | Expr::Compare(_, left, right)
+        | Expr::In(left, right) => {
+            collect_variables(left, names);
+            collect_variables(right, names);
+        },
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Int(i64),
+    LParen,
+    RParen,
+    Not,
+    And,
+    Or,
+    In,
+    Op(CompareOp),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(name) => write!(f, "'{}'", name),
+            Token::Str(s) => write!(f, "\"{}\"", s),
+            Token::Int(i) => write!(f, "{}", i),
+            Token::LParen => f.write_str("'('"),
+            Token::RParen => f.write_str("')'"),
+            Token::Not => f.write_str("'!'"),
+            Token::And => f.write_str("'&&'"),
+            Token::Or => f.write_str("'||'"),
+            Token::In => f.write_str("'in'"),
+            Token::Op(op) => f.write_str(match op {
+                CompareOp::Eq => "'=='",
+                CompareOp::Ne => "'!='",
+                CompareOp::Lt => "'<'",
+                CompareOp::Le => "'<='",
+                CompareOp::Gt => "'>'",
+                CompareOp::Ge => "'>='",
+            }),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let (token, len) = match (c, next) {
+            (c, _) if c.is_whitespace() => {
+                i += 1;
+                continue;
+            },
+            ('(', _) => (Token::LParen, 1),
+            (')', _) => (Token::RParen, 1),
+            ('&', Some('&')) => (Token::And, 2),
+            ('|', Some('|')) => (Token::Or, 2),
+            ('=', Some('=')) => (Token::Op(CompareOp::Eq), 2),
+            ('!', Some('=')) => (Token::Op(CompareOp::Ne), 2),
+            ('<', Some('=')) => (Token::Op(CompareOp::Le), 2),
+            ('>', Some('=')) => (Token::Op(CompareOp::Ge), 2),
+            ('!', _) => (Token::Not, 1),
+            ('<', _) => (Token::Op(CompareOp::Lt), 1),
+            ('>', _) => (Token::Op(CompareOp::Gt), 1),
+            ('"', _) | ('\'', _) => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&ch| ch == c)
+                    .ok_or_else(|| "unterminated string".to_string())?;
+                let text: String = chars[i + 1..i + 1 + end].iter().collect();
+                (Token::Str(text), end + 2)
+            },
+            (c, _) if c.is_ascii_digit() || (c == '-' && next.is_some_and(|n| n.is_ascii_digit())) => {
+                let len = 1 + chars[i + 1..].iter().take_while(|ch| ch.is_ascii_digit()).count();
+                let text: String = chars[i..i + len].iter().collect();
+                let number = text.parse().map_err(|_| format!("invalid number '{}'", text))?;
+                (Token::Int(number), len)
+            },
+            (c, _) if c.is_alphabetic() || c == '_' => {
+                let len = chars[i..].iter().take_while(|ch| ch.is_alphanumeric() || **ch == '_').count();
+                let word: String = chars[i..i + len].iter().collect();
+                let token = match word.as_str() {
+                    "in" => Token::In,
+                    _ => Token::Ident(word),
+                };
+                (token, len)
+            },
+            (c, _) => return Err(format!("unexpected character '{}'", c)),
+        };
+        tokens.push(token);
+        i += len;
+    }
+
+    Ok(tokens)
+}
+
+/// Recursive descent parser, from lowest to highest precedence:
+/// `||`, `&&`, `!`, comparisons and `in`, then names, literals and
+/// parenthesized expressions.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_not()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_not()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, String> {
+        if self.peek() == Some(&Token::Not) {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, String> {
+        let left = self.parse_primary()?;
+        match self.peek() {
+            Some(Token::Op(op)) => {
+                let op = *op;
+                self.pos += 1;
+                Ok(Expr::Compare(op, Box::new(left), Box::new(self.parse_primary()?)))
+            },
+            Some(Token::In) => {
+                self.pos += 1;
+                Ok(Expr::In(Box::new(left), Box::new(self.parse_primary()?)))
+            },
+            _ => Ok(left),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::LParen) => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(expr),
+                    Some(token) => Err(format!("expected ')' but found {}", token)),
+                    None => Err("missing ')'".to_string()),
+                }
+            },
+            Some(Token::Ident(name)) => Ok(match name.as_str() {
+                "true" => Expr::Literal(Value::Bool(true)),
+                "false" => Expr::Literal(Value::Bool(false)),
+                _ => Expr::Variable(name),
+            }),
+            Some(Token::Str(s)) => Ok(Expr::Literal(Value::String(s))),
+            Some(Token::Int(i)) => Ok(Expr::Literal(Value::Integer(i))),
+            Some(token) => Err(format!("unexpected {}", token)),
+            None => Err("unexpected end of condition".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables() -> HashMap<String, Value> {
+        HashMap::from([
+            ("yes".to_string(), Value::Bool(true)),
+            ("no".to_string(), Value::Bool(false)),
+            ("project_name".to_string(), Value::from("demo")),
+            ("use_docker".to_string(), Value::Bool(true)),
+            ("use_ci".to_string(), Value::Bool(false)),
+            ("workers".to_string(), Value::Integer(4)),
+            ("offset".to_string(), Value::Integer(-3)),
+            ("project_type".to_string(), Value::from("Library")),
+            ("deps".to_string(), Value::List(vec!["serde".to_string(), "tokio".to_string()])),
+        ])
+    }
+
+    fn evaluate(source: &str) -> bool {
+        Condition::parse(source).unwrap().evaluate(&variables())
+    }
+
+    fn variable(name: &str) -> Box<Expr> {
+        Box::new(Expr::Variable(name.to_string()))
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(
+            Condition::parse("a || b && c").unwrap().expr,
+            Expr::Or(variable("a"), Box::new(Expr::And(variable("b"), variable("c"))))
+        );
+        assert_eq!(
+            Condition::parse("!a && b == c").unwrap().expr,
+            Expr::And(
+                Box::new(Expr::Not(variable("a"))),
+                Box::new(Expr::Compare(CompareOp::Eq, variable("b"), variable("c")))
+            )
+        );
+        assert!(evaluate("yes || no && no"));
+        assert!(!evaluate("(yes || no) && no"));
+    }
+
+    #[test]
+    fn negative_integers() {
+        assert!(evaluate("offset == -3"));
+        assert!(evaluate("offset > -5 && offset < 0"));
+        assert!(evaluate("-10 <= offset"));
+        assert!(Condition::parse("offset > -").is_err());
+    }
+
+    #[test]
+    fn in_checks_lists_and_strings() {
+        assert!(evaluate(r#""tokio" in deps"#));
+        assert!(!evaluate(r#""tok" in deps"#));
+        assert!(evaluate(r#"!("clap" in deps)"#));
+        assert!(evaluate(r#""Lib" in project_type"#));
+        assert!(!evaluate(r#""lib" in project_type"#));
+        assert!(!evaluate(r#""x" in missing"#));
+    }
+
+    #[test]
+    fn invalid_conditions_fail_when_loaded() {
+        let error = serde_yaml::from_str::<Condition>("'yes &&'").unwrap_err();
+        assert!(error.to_string().contains("Invalid condition 'yes &&'"), "{}", error);
+
+        let condition: Condition = serde_yaml::from_str("'yes && !no'").unwrap();
+        assert_eq!(serde_yaml::to_string(&condition).unwrap().trim(), "yes && !no");
+    }
+
+    #[test]
+    fn bare_and_negated_variables() {
+        assert!(evaluate("use_docker"));
+        assert!(!evaluate("use_ci"));
+        assert!(evaluate("!use_ci"));
+        assert!(!evaluate("missing"));
+        assert!(evaluate("!missing"));
+    }
+
+    #[test]
+    fn logical_operators_and_grouping() {
+        assert!(evaluate("use_docker && !use_ci"));
+        assert!(evaluate("use_ci || use_docker"));
+        assert!(!evaluate("use_ci && (use_docker || true)"));
+        assert!(evaluate("!(use_ci && use_docker)"));
+        assert!(evaluate("use_ci && use_docker || project_name"));
+    }
+
+    #[test]
+    fn comparisons() {
+        assert!(evaluate(r#"project_type == "Library""#));
+        assert!(evaluate("project_type != 'Binary'"));
+        assert!(evaluate("workers >= 4 && workers < 8"));
+        assert!(evaluate("use_docker == true"));
+        assert!(!evaluate("project_type > 3"));
+        assert!(!evaluate(r#"missing == "x""#));
+    }
+
+    #[test]
+    fn parse_errors() {
+        for source in ["", "(use_docker", "use_docker &&", r#""unterminated"#, "a == == b", "a & b"] {
+            assert!(Condition::parse(source).is_err(), "{:?} should not parse", source);
+        }
+    }
+
+    #[test]
+    fn condition_variables() {
+        let condition = Condition::parse(r#"use_docker && ("tokio" in deps || workers > 2)"#).unwrap();
+        assert_eq!(condition.variables(), vec!["use_docker", "deps", "workers"]);
+    }
+}
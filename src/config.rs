@@ -1,9 +1,10 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use crate::paths::{config_file, expand_path, templates_dir};
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct Config {
     pub settings: Settings,
 }
@@ -12,6 +13,15 @@ pub struct Config {
 pub struct Settings {
     #[serde(default = "default_projects_dir")]
     pub projects_dir: PathBuf,
+    /// File written into each generated project recording the answers used
+    #[serde(default = "default_answers_file")]
+    pub answers_file: String,
+    /// Regular expression project names must match, e.g. `[a-z][a-z0-9-]*`
+    #[serde(default)]
+    pub project_name_pattern: Option<String>,
+    /// Extra template directories, searched before the user's templates
+    #[serde(default)]
+    pub template_dirs: Vec<PathBuf>,
 }
 
 fn default_projects_dir() -> PathBuf {
@@ -20,50 +30,73 @@ fn default_projects_dir() -> PathBuf {
         .join("Dev")
 }
 
+pub(crate) fn default_answers_file() -> String {
+    ".newnew-answers.yml".to_string()
+}
+
 impl Default for Settings {
     fn default() -> Self {
         Settings {
             projects_dir: default_projects_dir(),
+            answers_file: default_answers_file(),
+            project_name_pattern: None,
+            template_dirs: Vec::new(),
         }
     }
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Config {
-            settings: Settings::default(),
-        }
-    }
-}
+/// The config written on first use, documenting every setting
+const DEFAULT_CONFIG: &str = include_str!("../examples/newnew.toml");
 
 impl Config {
-    pub fn load() -> io::Result<Self> {
-        let config_path = get_config_path()?;
-        
-        if !config_path.exists() {
-            let default_config = include_str!("../examples/newnew.toml");
-            
-            if let Some(parent) = config_path.parent() {
-                fs::create_dir_all(parent)?;
+    /// Load `path`, or the config file in newnew's home if `None`, falling
+    /// back to the defaults if that doesn't exist yet. Path settings come
+    /// back expanded, with relative `template_dirs` resolved against the
+    /// config file's directory.
+    pub fn load(path: Option<&Path>) -> io::Result<Self> {
+        let config_path = match path {
+            Some(path) => expand_path(path)?,
+            None => config_file()?,
+        };
+
+        let config_str = if config_path.exists() {
+            fs::read_to_string(&config_path)?
+        } else if path.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Config file '{}' not found", config_path.display())
+            ));
+        } else {
+            DEFAULT_CONFIG.to_string()
+        };
+
+        let mut config: Config = toml::from_str(&config_str)
+            .map_err(io::Error::other)?;
+
+        let config_dir = config_path.parent().unwrap_or(Path::new("."));
+        config.settings.projects_dir = expand_path(&config.settings.projects_dir)?;
+        config.settings.template_dirs = config.settings.template_dirs
+            .iter()
+            .map(|dir| expand_path(dir).map(|dir| config_dir.join(dir)))
+            .collect::<io::Result<_>>()?;
+
+        Ok(config)
+    }
+
+    /// Set up newnew's home on first use: write the default config file,
+    /// unless `path` chooses another one, and create the user templates
+    /// directory, so there is something to edit
+    pub fn create_default_home(path: Option<&Path>) -> io::Result<()> {
+        if path.is_none() {
+            let config_path = config_file()?;
+            if !config_path.exists() {
+                if let Some(parent) = config_path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&config_path, DEFAULT_CONFIG)?;
             }
-            
-            fs::write(&config_path, default_config)?;
-            
-            return toml::from_str(default_config)
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, e));
         }
 
-        let config_str = fs::read_to_string(config_path)?;
-        toml::from_str(&config_str)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+        fs::create_dir_all(templates_dir()?)
     }
 }
-
-fn get_config_path() -> io::Result<PathBuf> {
-    dirs::home_dir()
-        .map(|p| p.join(".config").join("newnew").join("newnew.toml"))
-        .ok_or_else(|| io::Error::new(
-            io::ErrorKind::NotFound,
-            "Could not determine config directory"
-        ))
-} 
\ No newline at end of file
@@ -0,0 +1,215 @@
+use heck::{ToKebabCase, ToSnakeCase, ToTitleCase, ToUpperCamelCase};
+use minijinja::Environment;
+use std::collections::HashMap;
+use crate::condition::Condition;
+use crate::render::environment;
+use crate::value::Value;
+
+/// The variables visible to a template at some point of the generation,
+/// together with the operations that use them: evaluating `if`/`if-not`
+/// conditions, expanding `{name}` placeholders in commands and rendering
+/// file contents. Used both while prompting, where it grows as variables
+/// are answered, and while executing steps.
+pub struct Context {
+    variables: HashMap<String, Value>,
+    env: Environment<'static>,
+}
+
+impl Context {
+    pub fn new(variables: HashMap<String, Value>) -> Self {
+        Context {
+            variables,
+            env: environment(),
+        }
+    }
+
+    /// Set a variable, replacing any previous value
+    pub fn insert(&mut self, name: impl Into<String>, value: Value) {
+        self.variables.insert(name.into(), value);
+    }
+
+    /// Set `project_name` along with its `_snake`, `_kebab`, `_pascal` and
+    /// `_title` variants
+    pub fn insert_project_name(&mut self, name: &str) {
+        self.insert("project_name", Value::from(name));
+        self.insert("project_name_snake", Value::from(name.to_snake_case()));
+        self.insert("project_name_kebab", Value::from(name.to_kebab_case()));
+        self.insert("project_name_pascal", Value::from(name.to_upper_camel_case()));
+        self.insert("project_name_title", Value::from(name.to_title_case()));
+    }
+
+    pub fn variables(&self) -> &HashMap<String, Value> {
+        &self.variables
+    }
+
+    pub fn evaluate(&self, condition: &Condition) -> bool {
+        condition.evaluate(&self.variables)
+    }
+
+    /// Replace `{name}` placeholders, as used in `run` commands
+    pub fn expand(&self, text: &str) -> String {
+        let mut result = text.to_string();
+        for (key, value) in &self.variables {
+            result = result.replace(&format!("{{{}}}", key), &value.to_string());
+        }
+        result
+    }
+
+    /// Render text with the template engine. Supports Jinja syntax
+    /// (`{{ var }}`, `{% if %}`, `{% for %}`, filters) as well as the legacy
+    /// `{var}` placeholders, so existing templates keep working. Values keep
+    /// their types, so booleans are real booleans and multiselect values are
+    /// lists.
+    pub fn render(&self, content: &str) -> Result<String, String> {
+        self.env
+            .render_str(&self.convert_placeholders(content), &self.variables)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Turn `{name}` placeholders of known variables into Jinja expressions
+    /// before rendering, rather than replacing them afterwards, so `{% raw %}`
+    /// blocks protect them too. Text inside Jinja tags and comments is left
+    /// alone.
+    fn convert_placeholders(&self, content: &str) -> String {
+        let mut result = String::with_capacity(content.len());
+        let mut rest = content;
+        while let Some(start) = rest.find('{') {
+            result.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            let close = match rest.as_bytes().get(1) {
+                Some(b'{') => Some("}}"),
+                Some(b'%') => Some("%}"),
+                Some(b'#') => Some("#}"),
+                _ => None,
+            };
+            if let Some(close) = close {
+                let mut len = rest.find(close).map_or(rest.len(), |end| end + close.len());
+                if tag_name(&rest[..len]) == "raw" {
+                    len += raw_block_len(&rest[len..]);
+                }
+                result.push_str(&rest[..len]);
+                rest = &rest[len..];
+                continue;
+            }
+
+            let name_len = rest[1..]
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len() - 1);
+            let name = &rest[1..name_len + 1];
+            let placeholder = self.variables
+                .get(name)
+                .filter(|_| rest[name_len + 1..].starts_with('}'))
+                .map(|value| (name, value));
+            match placeholder {
+                Some((name, value)) => {
+                    // Lists are joined with commas, as in commands
+                    let filter = if matches!(value, Value::List(_)) { " | join(',')" } else { "" };
+                    result.push_str(&format!("{{{{ {}{} }}}}", name, filter));
+                    rest = &rest[name.len() + 2..];
+                },
+                None => {
+                    result.push('{');
+                    rest = &rest[1..];
+                },
+            }
+        }
+        result.push_str(rest);
+        result
+    }
+}
+
+/// The name of a `{% ... %}` tag such as `raw`, ignoring whitespace control
+fn tag_name(tag: &str) -> &str {
+    tag.strip_prefix("{%")
+        .and_then(|tag| tag.strip_suffix("%}"))
+        .unwrap_or_default()
+        .trim_matches(|c: char| c == '-' || c == '+' || c.is_whitespace())
+}
+
+/// Length of the rest of a raw block, up to and including `{% endraw %}`
+fn raw_block_len(text: &str) -> usize {
+    let mut offset = 0;
+    while let Some(start) = text[offset..].find("{%") {
+        let tag = &text[offset + start..];
+        let len = tag.find("%}").map_or(tag.len(), |end| end + 2);
+        offset += start + len;
+        if tag_name(&tag[..len]) == "endraw" {
+            return offset;
+        }
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> Context {
+        Context::new(HashMap::from([
+            ("project_name".to_string(), Value::from("demo")),
+            ("use_docker".to_string(), Value::Bool(true)),
+            ("use_ci".to_string(), Value::Bool(false)),
+            ("project_type".to_string(), Value::from("Library")),
+            ("workers".to_string(), Value::Integer(4)),
+            ("deps".to_string(), Value::List(vec!["serde".to_string(), "tokio".to_string()])),
+        ]))
+    }
+
+    #[test]
+    fn expands_placeholders() {
+        assert_eq!(context().expand("cargo new {project_name} --{project_type}"), "cargo new demo --Library");
+        assert_eq!(context().expand("add {deps} {unknown}"), "add serde,tokio {unknown}");
+    }
+
+    #[test]
+    fn renders_jinja_with_typed_values() {
+        let rendered = context()
+            .render("{% if use_docker %}docker{% endif %}{% for dep in deps %} {{ dep }}{% endfor %} {project_name}")
+            .unwrap();
+        assert_eq!(rendered, "docker serde tokio demo");
+        assert!(context().render("{% if %}").is_err());
+    }
+
+    #[test]
+    fn raw_blocks_protect_placeholders() {
+        let rendered = context()
+            .render("{% raw %}{project_name} {{ deps }}{% endraw %} {project_name} {%- raw %} {deps}{%- endraw %}")
+            .unwrap();
+        assert_eq!(rendered, "{project_name} {{ deps }} demo {deps}");
+    }
+
+    #[test]
+    fn single_braces_pass_through() {
+        let code = "fn main() { let v = json!({ \"a\": {} }); }\n{unknown} {project_name {} }";
+        assert_eq!(context().render(code).unwrap(), code);
+        assert_eq!(context().render("{deps} {{ \"{project_name}\" }}").unwrap(), "serde,tokio {project_name}");
+    }
+
+    #[test]
+    fn case_filters() {
+        let rendered = context()
+            .render("{{ 'My Cool App' | snake_case }} {{ 'My Cool App' | kebab_case }} {{ 'my cool app' | camel_case }} \
+                     {{ 'my cool app' | pascal_case }} {{ 'my-cool-app' | title_case }} {{ 'My Cool App' | screaming_snake_case }}")
+            .unwrap();
+        assert_eq!(rendered, "my_cool_app my-cool-app myCoolApp MyCoolApp My Cool App MY_COOL_APP");
+    }
+
+    #[test]
+    fn project_name_variants() {
+        let mut context = context();
+        context.insert_project_name("My Cool App");
+        assert_eq!(
+            context.expand("{project_name_snake} {project_name_kebab} {project_name_pascal} {project_name_title}"),
+            "my_cool_app my-cool-app MyCoolApp My Cool App"
+        );
+    }
+
+    #[test]
+    fn later_values_replace_earlier_ones() {
+        let mut context = context();
+        context.insert("workers", Value::Integer(16));
+        assert!(context.evaluate(&Condition::parse("workers > 8").unwrap()));
+        assert_eq!(context.variables().get("workers"), Some(&Value::Integer(16)));
+    }
+}
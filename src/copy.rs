@@ -0,0 +1,187 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use crate::context::Context;
+use crate::paths::is_inside;
+use crate::template::{CopyStep, list_files_recursive};
+
+/// How many leading bytes are inspected when deciding if a file is binary
+const BINARY_SNIFF_LEN: usize = 8000;
+
+/// A file produced by a copy step, with variables already expanded
+#[derive(Debug, Clone)]
+pub struct RenderedFile {
+    /// Destination relative to the project directory
+    pub path: PathBuf,
+    pub content: Vec<u8>,
+    /// Permissions of the source file, so executable scripts stay executable
+    pub permissions: fs::Permissions,
+}
+
+impl RenderedFile {
+    /// Destination path with `/` separators, as recorded in answers files
+    pub fn key(&self) -> String {
+        self.path.to_string_lossy().replace('\\', "/")
+    }
+
+    /// Contents as text, or `None` for binary files
+    pub fn text(&self) -> Option<&str> {
+        std::str::from_utf8(&self.content).ok()
+    }
+
+    /// Write the file below `project_path`, creating parent directories as
+    /// needed.
+    pub fn write_to(&self, project_path: &Path) -> io::Result<()> {
+        let dest = project_path.join(&self.path);
+
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&dest, &self.content)
+            .and_then(|_| fs::set_permissions(&dest, self.permissions.clone()))
+            .map_err(|e| io::Error::new(
+                e.kind(),
+                format!("Failed to write file '{}': {}", dest.display(), e)
+            ))
+    }
+}
+
+/// A file read by a copy step
+pub(crate) struct SourceFile {
+    pub path: PathBuf,
+    /// Path below the step's source directory with `/` separators, or the
+    /// file name if the source is a single file
+    pub relative: String,
+    /// Whether the contents go through the template engine, as opposed to
+    /// being copied verbatim because of `raw` or `no_render`
+    pub render: bool,
+}
+
+/// The files a copy step reads from `source`: the source itself, or every
+/// file below it that matches the step's include/exclude globs
+pub(crate) fn source_files(copy: &CopyStep, source: &Path) -> io::Result<Vec<SourceFile>> {
+    let no_render = build_globs(&copy.no_render)?;
+    let source_file = |path: PathBuf, relative: String| SourceFile {
+        render: !copy.raw && !no_render.is_match(&relative),
+        path,
+        relative,
+    };
+
+    if !source.is_dir() {
+        let file_name = source.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        return Ok(vec![source_file(source.to_path_buf(), file_name)]);
+    }
+
+    let include = build_globs(&copy.include)?;
+    let exclude = build_globs(&copy.exclude)?;
+
+    let mut files = Vec::new();
+    for relative in list_files_recursive(source)? {
+        let key = relative.to_string_lossy().replace('\\', "/");
+        if (!copy.include.is_empty() && !include.is_match(&key)) || exclude.is_match(&key) {
+            continue;
+        }
+        files.push(source_file(source.join(&relative), key));
+    }
+    Ok(files)
+}
+
+/// Read the source of a copy step and render its contents without touching
+/// the project directory. If the source is a directory, every file below it
+/// that matches the step's include/exclude globs is rendered, with variables
+/// in file and directory names expanded as well. Binary files, and files
+/// matched by `raw`/`no_render`, are copied verbatim. `copy.from` is
+/// relative to `template_dir`, the directory the template was loaded from.
+pub fn render_copy(copy: &CopyStep, template_dir: &Path, context: &Context) -> io::Result<Vec<RenderedFile>> {
+    let source = copy.source(template_dir)?;
+    let to = render_path(&copy.to, context)?;
+
+    let mut files = Vec::new();
+    for file in source_files(copy, &source)? {
+        let dest = if source.is_dir() {
+            to.join(render_path(&file.relative, context)?)
+        } else {
+            to.clone()
+        };
+        files.push(render_file(&file.path, dest, file.render, context)?);
+    }
+    Ok(files)
+}
+
+fn render_file(source: &Path, dest: PathBuf, render: bool, context: &Context) -> io::Result<RenderedFile> {
+    let content = fs::read(source)
+        .map_err(|e| io::Error::new(
+            e.kind(),
+            format!("Failed to read template file '{}': {}", source.display(), e)
+        ))?;
+    let permissions = fs::metadata(source)?.permissions();
+
+    let content = match std::str::from_utf8(&content) {
+        Ok(text) if render && !is_binary(&content) => context.render(text)
+            .map_err(|e| io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Failed to render template file '{}': {}", source.display(), e)
+            ))?
+            .into_bytes(),
+        _ => content,
+    };
+
+    Ok(RenderedFile {
+        path: dest,
+        content,
+        permissions,
+    })
+}
+
+/// Treat files with NUL bytes near the start as binary, like git does
+pub(crate) fn is_binary(content: &[u8]) -> bool {
+    content.iter().take(BINARY_SNIFF_LEN).any(|&byte| byte == 0)
+}
+
+/// Expand variables in a destination path such as `src/{project_name}`.
+/// Fails if the result would leave the project directory, e.g. because a
+/// variable's value contains `..`.
+fn render_path(path: &str, context: &Context) -> io::Result<PathBuf> {
+    let rendered = context.render(path)
+        .map_err(|e| io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Failed to render path '{}': {}", path, e)
+        ))?;
+    if !is_inside(Path::new(&rendered)) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Copy destination '{}' (from '{}') must be a relative path inside the project", rendered, path)
+        ));
+    }
+
+    Ok(Path::new(&rendered)
+        .components()
+        .filter(|component| !matches!(component, Component::CurDir))
+        .collect())
+}
+
+fn build_globs(patterns: &[String]) -> io::Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern)
+            .map_err(|e| io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid glob pattern '{}': {}", pattern, e)
+            ))?;
+        builder.add(glob);
+    }
+    builder.build()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))
+}
+
+/// Write rendered files into the project directory
+pub fn write_rendered(project_path: &Path, files: &[RenderedFile]) -> io::Result<()> {
+    for file in files {
+        file.write_to(project_path)?;
+    }
+    Ok(())
+}
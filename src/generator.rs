@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+use crate::answers::{RecordedAnswers, write_answers, write_merge_base};
+use crate::config::default_answers_file;
+use crate::copy::{render_copy, write_rendered};
+use crate::project::{ProjectConfig, resolve_variables, step_skip_reason, validate_project_name};
+use crate::prompt::Prompter;
+use crate::report::{Reporter, SilentReporter};
+use crate::template::Template;
+use crate::utils::check_command_exists;
+use crate::value::Value;
+
+/// Generates a project from a template without going through the CLI, e.g.
+/// from a service that already knows the answers:
+///
+/// ```no_run
+/// use std::collections::HashMap;
+/// use newnew::{Generator, SilentReporter, Value, load_templates};
+///
+/// let templates = load_templates(None, &mut SilentReporter)?;
+/// let variables = HashMap::from([("use_github".to_string(), Value::Bool(false))]);
+/// Generator::new(templates["rust"].clone(), variables, "/srv/projects/billing").generate()?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct Generator {
+    pub template: Template,
+    pub variables: HashMap<String, Value>,
+    /// Directory to create; its name becomes `project_name`
+    pub target: PathBuf,
+    /// File name to record the answers under in the project, if any
+    pub answers_file: Option<String>,
+    /// Leave a partially created project in place if generation fails
+    pub keep_on_failure: bool,
+    /// Where progress and warnings go; nowhere by default
+    pub reporter: Box<dyn Reporter>,
+}
+
+impl Generator {
+    pub fn new(template: Template, variables: HashMap<String, Value>, target: impl Into<PathBuf>) -> Self {
+        Generator {
+            template,
+            variables,
+            target: target.into(),
+            answers_file: Some(default_answers_file()),
+            keep_on_failure: false,
+            reporter: Box::new(SilentReporter),
+        }
+    }
+
+    /// Generate the project, using defaults for variables that weren't
+    /// supplied. Fails if a variable has neither. Returns the project
+    /// directory.
+    pub fn generate(&mut self) -> io::Result<PathBuf> {
+        let config = self.config(None)?;
+        create_project(&config, self.keep_on_failure, &mut *self.reporter)
+    }
+
+    /// Generate the project, asking `prompter` for variables that weren't
+    /// supplied
+    pub fn generate_with(&mut self, prompter: &mut dyn Prompter) -> io::Result<PathBuf> {
+        let config = self.config(Some(prompter))?;
+        create_project(&config, self.keep_on_failure, &mut *self.reporter)
+    }
+
+    /// Resolve the variables without generating anything
+    pub fn config(&mut self, prompter: Option<&mut dyn Prompter>) -> io::Result<ProjectConfig> {
+        let name = self.target
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        validate_project_name(&name, None)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        let mut supplied = self.variables.clone();
+        supplied.remove("project_name");
+        let resolved = resolve_variables(
+            &self.template.id,
+            &self.template,
+            &name,
+            supplied,
+            prompter,
+            false,
+            &mut *self.reporter,
+        )?;
+        if !resolved.missing.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Missing values for required variables: {}", resolved.missing.join(", "))
+            ));
+        }
+
+        Ok(ProjectConfig {
+            name,
+            template_name: self.template.id.clone(),
+            template: self.template.clone(),
+            base_path: self.target
+                .parent()
+                .map(|parent| parent.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            variables: resolved.variables,
+            builtins: resolved.builtins,
+            answers_file: self.answers_file.clone(),
+        })
+    }
+}
+
+/// Create the project described by `config`: make its directory, run the
+/// template's steps and record the answers. Generation is all-or-nothing;
+/// unless `keep_on_failure` is set, whatever was created is removed again if
+/// a step fails. Returns the project directory.
+pub fn create_project(
+    config: &ProjectConfig,
+    keep_on_failure: bool,
+    reporter: &mut dyn Reporter,
+) -> io::Result<PathBuf> {
+    reporter.progress(&format!("🚀 Creating '{}' from template '{}'", config.name, config.template_name));
+
+    let project_path = config.project_path()?;
+
+    // Create base directory if it doesn't exist
+    if let Some(base_path) = project_path.parent() {
+        fs::create_dir_all(base_path)?;
+    }
+
+    let existed = project_path.exists();
+    if existed && (!project_path.is_dir() || fs::read_dir(&project_path)?.next().is_some()) {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("'{}' already exists and is not an empty directory", project_path.display())
+        ));
+    }
+    fs::create_dir_all(&project_path)?;  // Create project directory immediately
+
+    // Generation is all-or-nothing: on failure remove whatever was created
+    if let Err(e) = generate_project(config, &project_path, reporter) {
+        if keep_on_failure {
+            reporter.warning(&format!("Keeping partially created project at '{}'", project_path.display()));
+        } else if let Err(cleanup_error) = remove_project(&project_path, existed) {
+            reporter.warning(&format!("Failed to clean up '{}': {}", project_path.display(), cleanup_error));
+        } else {
+            reporter.progress(&format!("🧹 Removed partially created project at '{}'", project_path.display()));
+        }
+        return Err(e);
+    }
+
+    Ok(project_path)
+}
+
+/// Remove a partially generated project. A directory that existed (empty)
+/// before generation is emptied rather than deleted.
+fn remove_project(project_path: &Path, existed: bool) -> io::Result<()> {
+    if !existed {
+        return fs::remove_dir_all(project_path);
+    }
+
+    for entry in fs::read_dir(project_path)? {
+        let path = entry?.path();
+        if path.is_dir() && !path.is_symlink() {
+            fs::remove_dir_all(&path)?;
+        } else {
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+fn generate_project(config: &ProjectConfig, project_path: &Path, reporter: &mut dyn Reporter) -> io::Result<()> {
+    let context = config.context(project_path);
+
+    // Files written by copy steps, recorded so `newnew update` can merge
+    // later template changes into the project
+    let mut rendered = Vec::new();
+
+    // Process steps in sequence
+    for step in &config.template.steps {
+        reporter.progress(&format!("⚡ Checking step: {}", step.name));
+
+        // Check if step should be run based on conditions
+        if let Some(reason) = step_skip_reason(step, &context) {
+            reporter.progress(&format!("  ↪ Skipped: {}", reason));
+            continue;
+        }
+
+        reporter.progress("  ▶ Executing step");
+
+        // Check if required command exists
+        if let Some(check_cmd) = &step.check {
+            if !check_command_exists(check_cmd) {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    step.error.as_deref().unwrap_or(&format!("{} is not installed", check_cmd))
+                ));
+            }
+        }
+
+        // Handle copy step
+        if let Some(copy) = &step.copy {
+            let files = render_copy(copy, &config.template.dir, &context)?;
+            write_rendered(project_path, &files)?;
+            rendered.extend(files);
+        }
+
+        // Handle run command
+        if let Some(run_cmd) = &step.run {
+            // Handle multiline commands (commands with |)
+            for cmd in run_cmd.split('\n') {  // Split by newlines instead of |
+                let cmd = cmd.trim();
+                if cmd.is_empty() {
+                    continue;
+                }
+                
+                let expanded_cmd = context.expand(cmd);
+                
+                if let Err(e) = run_command(&expanded_cmd, project_path, step.retries, reporter) {
+                    if step.continue_on_error {
+                        reporter.warning(&format!("{} (continuing)", e));
+                        continue;
+                    }
+                    return Err(io::Error::new(
+                        e.kind(),
+                        format!("Step '{}' failed: {}", step.name, e)
+                    ));
+                }
+            }
+        }
+    }
+
+    // Record the answers so the generation can be audited and replayed
+    if let Some(answers_file) = &config.answers_file {
+        let recorded = RecordedAnswers::new(config, &rendered);
+        write_answers(&project_path.join(answers_file), &recorded)?;
+        write_merge_base(project_path, config, &rendered)?;
+    }
+
+    Ok(())
+}
+
+/// Run a shell command in the project directory, retrying up to `retries`
+/// times if it exits with a non-zero status.
+fn run_command(cmd: &str, project_path: &Path, retries: u32, reporter: &mut dyn Reporter) -> io::Result<()> {
+    let mut attempt = 0;
+    loop {
+        // Use shell to execute command to properly handle redirection
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(cmd)
+            .current_dir(project_path)
+            .status()
+            .map_err(|e| io::Error::other(
+                format!("Failed to run command '{}': {}", cmd, e)
+            ))?;
+
+        if status.success() {
+            return Ok(());
+        }
+
+        if attempt >= retries {
+            return Err(io::Error::other(match status.code() {
+                Some(code) => format!("command '{}' exited with code {}", cmd, code),
+                None => format!("command '{}' was terminated by a signal", cmd),
+            }));
+        }
+
+        attempt += 1;
+        reporter.progress(&format!("  ↻ Retrying ({}/{}): {}", attempt, retries, cmd));
+        thread::sleep(Duration::from_secs(attempt.into()));
+    }
+}
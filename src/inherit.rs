@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use crate::template::{Step, Template, hash_content};
+
+/// Merge the templates `template` extends into it, nearest parent last.
+/// `templates` holds every template as parsed, keyed by the name it is
+/// selected by, before their own `extends` are applied.
+///
+/// The child starts from its parent's variables and steps:
+///
+/// - A variable with the same name as a parent variable replaces it in place;
+///   other variables are added after the parent's
+/// - A step with `remove: true` drops the parent step of that name
+/// - A step with `before:` or `after:` is inserted next to the named parent
+///   step
+/// - Any other step with the same name as a parent step replaces it in place;
+///   the rest are added after the parent's
+pub(crate) fn resolve_extends(template: Template, templates: &HashMap<String, Template>) -> Result<Template, String> {
+    resolve_chain(template, templates, &mut Vec::new())
+}
+
+fn resolve_chain(
+    template: Template,
+    templates: &HashMap<String, Template>,
+    chain: &mut Vec<String>,
+) -> Result<Template, String> {
+    let Some(parent_name) = template.extends.clone() else {
+        return Ok(template);
+    };
+
+    chain.push(template.id.clone());
+    let parent_id = parent_id(&template.id, &parent_name, templates)
+        .ok_or_else(|| format!("extends unknown template '{}'", parent_name))?;
+    if chain.contains(&parent_id) {
+        return Err(format!("extends itself through {} -> {}", chain.join(" -> "), parent_id));
+    }
+
+    let parent = resolve_chain(templates[&parent_id].clone(), templates, chain)?;
+    inherit(template, &parent)
+        .map_err(|e| format!("extends '{}': {}", parent_name, e))
+}
+
+/// Templates from a source extend others from the same source first, so
+/// `extends: base` in `acme/service` means `acme/base` if there is one
+fn parent_id(child_id: &str, parent_name: &str, templates: &HashMap<String, Template>) -> Option<String> {
+    let same_source = child_id
+        .rsplit_once('/')
+        .map(|(source, _)| format!("{}/{}", source, parent_name));
+
+    same_source
+        .into_iter()
+        .chain([parent_name.to_string()])
+        .find(|id| templates.contains_key(id))
+}
+
+fn inherit(mut child: Template, parent: &Template) -> Result<Template, String> {
+    let mut variables = parent.variables.clone();
+    for var in child.variables {
+        match variables.iter().position(|existing| existing.name == var.name) {
+            Some(index) => variables[index] = var,
+            None => variables.push(var),
+        }
+    }
+
+    // Inherited copy steps keep copying from the parent's directory
+    let mut steps: Vec<Step> = parent.steps.clone();
+    for step in &mut steps {
+        if let Some(copy) = &mut step.copy {
+            copy.dir.get_or_insert_with(|| parent.dir.clone());
+        }
+    }
+
+    for step in child.steps {
+        let position = |steps: &[Step], name: &str| steps
+            .iter()
+            .position(|existing| existing.name == name)
+            .ok_or_else(|| format!("step '{}' refers to '{}', which the parent doesn't have", step.name, name));
+
+        if step.remove {
+            let index = steps.iter()
+                .position(|existing| existing.name == step.name)
+                .ok_or_else(|| format!("can't remove step '{}', which the parent doesn't have", step.name))?;
+            steps.remove(index);
+        } else if let Some(target) = &step.before {
+            let index = position(&steps, target)?;
+            steps.insert(index, step);
+        } else if let Some(target) = &step.after {
+            // Keep steps inserted after the same step in the order given
+            let mut index = position(&steps, target)? + 1;
+            while steps.get(index).is_some_and(|next| next.after.as_ref() == Some(target)) {
+                index += 1;
+            }
+            steps.insert(index, step);
+        } else {
+            match steps.iter().position(|existing| existing.name == step.name) {
+                Some(index) => steps[index] = step,
+                None => steps.push(step),
+            }
+        }
+    }
+
+    child.variables = variables;
+    child.steps = steps;
+    // Changes to the parent are changes to the child, as far as updating
+    // generated projects is concerned
+    child.hash = hash_content(format!("{}{}", child.hash, parent.hash));
+    Ok(child)
+}
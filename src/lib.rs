@@ -0,0 +1,42 @@
+//! Project scaffolding from YAML templates.
+//!
+//! The `newnew` binary is a thin CLI over this library. Other tools can load
+//! templates with [`load_templates`] and generate projects with a
+//! [`Generator`], answering questions through their own [`Prompter`] and
+//! following progress through their own [`Reporter`].
+
+mod answers;
+mod builtins;
+mod condition;
+mod config;
+mod context;
+mod copy;
+mod generator;
+mod inherit;
+mod lint;
+mod paths;
+mod plan;
+mod project;
+mod prompt;
+mod render;
+mod report;
+mod sources;
+mod template;
+mod update;
+mod utils;
+mod value;
+
+pub use condition::Condition;
+pub use generator::{Generator, create_project};
+pub use lint::{LintOptions, lint_templates};
+pub use paths::{expand_path, newnew_home};
+pub use plan::print_plan;
+pub use project::{ProjectConfig, ProjectOptions, prompt_project_config};
+pub use prompt::{Prompter, ScriptedPrompter, TerminalPrompter};
+pub use report::{Reporter, SilentReporter};
+pub use sources::{AddSourceOptions, Source, add_source, load_sources, update_sources};
+pub use template::{
+    CopyStep, Step, Template, TemplateRoot, TemplateVariable, VariableType, load_templates, template_roots,
+};
+pub use update::{UpdateOptions, update_project};
+pub use value::Value;
@@ -0,0 +1,379 @@
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::builtins::BUILTIN_VARIABLES;
+use crate::copy::{is_binary, source_files};
+use crate::inherit::resolve_extends;
+use crate::render::undeclared_variables;
+use crate::report::Reporter;
+use crate::template::{
+    BUNDLE_FILE, CopyStep, Template, TemplateFile, TemplateRoot, VariableType, template_roots,
+};
+
+pub struct LintOptions {
+    /// Template name, or path to a template file; all templates if `None`
+    pub template: Option<String>,
+    /// Templates directory (defaults to every template root)
+    pub dir: Option<PathBuf>,
+    /// Config file to use instead of `newnew.toml` in newnew's home
+    pub config_file: Option<PathBuf>,
+}
+
+/// Problems found in a single template file
+#[derive(Default)]
+struct Report {
+    errors: Vec<String>,
+    warnings: Vec<String>,
+}
+
+/// Check templates for mistakes that would only show up when generating a
+/// project, reporting what was found in each to `reporter`. Returns `false`
+/// if any errors were found.
+pub fn lint_templates(options: LintOptions, reporter: &mut dyn Reporter) -> io::Result<bool> {
+    let (files, roots) = find_template_files(&options, reporter)?;
+    let templates = parsed_templates(&roots, reporter);
+
+    let mut errors = 0;
+    let mut warnings = 0;
+    for file in &files {
+        reporter.progress(&format!("🔍 {}", file.path.display()));
+        let report = lint_file(file, &templates);
+
+        for error in &report.errors {
+            reporter.progress(&format!("  ❌ {}", error));
+        }
+        for warning in &report.warnings {
+            reporter.progress(&format!("  ⚠️  {}", warning));
+        }
+        if report.errors.is_empty() && report.warnings.is_empty() {
+            reporter.progress("  ✅ No problems found");
+        }
+
+        errors += report.errors.len();
+        warnings += report.warnings.len();
+    }
+
+    if errors > 0 {
+        reporter.progress(&format!(
+            "\n❌ Found {} error(s) and {} warning(s) in {} template(s)",
+            errors, warnings, files.len()
+        ));
+    } else {
+        reporter.progress(&format!("\n✨ {} template(s) passed with {} warning(s)", files.len(), warnings));
+    }
+    Ok(errors == 0)
+}
+
+/// Template files to lint, and the roots templates they extend are looked
+/// up in
+fn find_template_files(
+    options: &LintOptions,
+    reporter: &mut dyn Reporter,
+) -> io::Result<(Vec<TemplateFile>, Vec<TemplateRoot>)> {
+    // A path to a template file, e.g. when linting a template repository
+    if let Some(template) = &options.template {
+        let path = PathBuf::from(template);
+        if path.is_file() {
+            let is_bundle = path.file_name().is_some_and(|name| name == BUNDLE_FILE);
+            let parent = path.parent().map(Path::to_path_buf).unwrap_or_default();
+            // Other templates are next to the bundle, or next to the file
+            let (id_source, root) = if is_bundle {
+                (parent.clone(), parent.parent().map(Path::to_path_buf).unwrap_or_default())
+            } else {
+                (path.clone(), parent.clone())
+            };
+            let file = TemplateFile {
+                id: id_source.file_stem().and_then(|s| s.to_str()).unwrap_or("unknown").to_string(),
+                dir: options.dir.clone().unwrap_or(parent),
+                path,
+            };
+
+            let mut roots = vec![TemplateRoot::new("dir", root)];
+            roots.extend(template_roots(options.config_file.as_deref(), reporter).unwrap_or_default());
+            return Ok((vec![file], roots));
+        }
+    }
+
+    let roots = match &options.dir {
+        Some(dir) => {
+            if let Err(e) = fs::read_dir(dir) {
+                return Err(io::Error::new(e.kind(), format!("Failed to read '{}': {}", dir.display(), e)));
+            }
+            vec![TemplateRoot::new("dir", dir.clone())]
+        },
+        None => template_roots(options.config_file.as_deref(), reporter)?,
+    };
+
+    // Only the templates that are actually used, not ones hidden by a
+    // template of the same name in an earlier root
+    let mut seen = HashSet::new();
+    let mut files = Vec::new();
+    for root in &roots {
+        for file in root.template_files(reporter)? {
+            if !seen.insert(file.id.clone()) {
+                continue;
+            }
+            if options.template.as_deref().is_none_or(|template| template == file.id) {
+                files.push(file);
+            }
+        }
+    }
+
+    if files.is_empty() {
+        let searched: Vec<String> = roots.iter().map(|root| root.dir.display().to_string()).collect();
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            match &options.template {
+                Some(template) => format!("Template '{}' not found in '{}'", template, searched.join("', '")),
+                None => format!("No templates found in '{}'", searched.join("', '")),
+            }
+        ));
+    }
+
+    // A template repository may extend installed templates
+    let mut lookup = roots;
+    if options.dir.is_some() {
+        lookup.extend(template_roots(options.config_file.as_deref(), reporter).unwrap_or_default());
+    }
+    Ok((files, lookup))
+}
+
+/// Templates that can be extended, as parsed. Ones that don't parse are
+/// reported when they are linted themselves.
+fn parsed_templates(roots: &[TemplateRoot], reporter: &mut dyn Reporter) -> HashMap<String, Template> {
+    let mut templates = HashMap::new();
+    for root in roots {
+        for file in root.template_files(reporter).unwrap_or_default() {
+            if templates.contains_key(&file.id) {
+                continue;
+            }
+            let parsed = fs::read_to_string(&file.path)
+                .ok()
+                .and_then(|content| serde_yaml::from_str::<Template>(&content).ok());
+            if let Some(mut template) = parsed {
+                template.id = file.id.clone();
+                template.dir = file.dir;
+                templates.insert(file.id, template);
+            }
+        }
+    }
+    templates
+}
+
+fn lint_file(file: &TemplateFile, templates: &HashMap<String, Template>) -> Report {
+    let mut report = Report::default();
+    let template_dir = &file.dir;
+
+    let content = match fs::read_to_string(&file.path) {
+        Ok(content) => content,
+        Err(e) => {
+            report.errors.push(format!("Failed to read template: {}", e));
+            return report;
+        }
+    };
+
+    // Parse while collecting keys serde would otherwise silently ignore
+    let mut unknown_keys = Vec::new();
+    let parsed: Result<Template, _> = serde_ignored::deserialize(
+        serde_yaml::Deserializer::from_str(&content),
+        |path| unknown_keys.push(path.to_string()),
+    );
+    let template = match parsed {
+        Ok(template) => template,
+        Err(e) => {
+            report.errors.push(format!("Invalid template: {}", e));
+            return report;
+        }
+    };
+    for key in unknown_keys {
+        report.errors.push(format!("Unknown key '{}'", key));
+    }
+
+    if template.extends.is_none() {
+        for step in &template.steps {
+            if step.before.is_some() || step.after.is_some() || step.remove {
+                report.warnings.push(format!("step '{}': before, after and remove only apply with extends", step.name));
+            }
+        }
+    }
+
+    // Check the template as it will be used, with its parents merged in
+    let mut template = template;
+    template.id = file.id.clone();
+    template.dir = file.dir.clone();
+    let template = match resolve_extends(template, templates) {
+        Ok(template) => template,
+        Err(e) => {
+            report.errors.push(e);
+            return report;
+        }
+    };
+    if template.steps.is_empty() {
+        report.warnings.push("Template has no steps".to_string());
+    }
+
+    let declared: HashSet<&str> = template.variables.iter().map(|var| var.name.as_str()).collect();
+    let mut all_variables = declared.clone();
+    all_variables.extend(BUILTIN_VARIABLES);
+
+    // Variables are prompted in order, so conditions and computed values can
+    // only refer to variables defined before them. The project directory is
+    // only known once all variables are.
+    let mut defined: HashSet<&str> = BUILTIN_VARIABLES
+        .iter()
+        .copied()
+        .filter(|name| *name != "project_dir")
+        .collect();
+    for var in &template.variables {
+        let label = format!("variable '{}'", var.name);
+
+        if matches!(var.type_, VariableType::Select | VariableType::Multiselect)
+            && var.options.as_ref().is_none_or(Vec::is_empty)
+        {
+            report.errors.push(format!("{}: {} variables need a list of options", label, var.type_));
+        }
+
+        if let Err(e) = var.pattern_regex() {
+            report.errors.push(format!("{}: {}", label, e));
+        } else if let Err(e) = var.default_value() {
+            report.errors.push(format!("{}: default: {}", label, e));
+        }
+
+        // Rules that can never apply to values of the variable's type
+        let text_rules = var.pattern.is_some() || var.min_length.is_some() || var.max_length.is_some();
+        if text_rules && matches!(var.type_, VariableType::Boolean | VariableType::Integer) {
+            report.warnings.push(format!("{}: pattern and length rules are ignored for {} variables", label, var.type_));
+        }
+        if (var.min.is_some() || var.max.is_some()) && var.type_ != VariableType::Integer {
+            report.warnings.push(format!("{}: min and max only apply to integer variables", label));
+        }
+
+        match (&var.value, &var.command) {
+            (Some(_), Some(_)) => {
+                report.errors.push(format!("{}: use either value or command, not both", label));
+            },
+            (Some(_), None) if !var.prompt.is_empty() => {
+                report.warnings.push(format!("{}: prompt is ignored for variables with a value", label));
+            },
+            (None, None) if var.prompt.is_empty() => {
+                report.errors.push(format!("{}: needs a prompt, value or command", label));
+            },
+            _ => {}
+        }
+
+        let undefined_problem = |name: &str| if declared.contains(name) { "is defined after it" } else { "is not defined" };
+        for condition in [&var.if_condition, &var.if_not].into_iter().flatten() {
+            for name in condition.variables() {
+                if !defined.contains(name) {
+                    report.errors.push(format!("{}: condition refers to '{}', which {}", label, name, undefined_problem(name)));
+                }
+            }
+        }
+        for expression in [&var.value, &var.command].into_iter().flatten() {
+            for name in placeholders(expression) {
+                if !defined.contains(name.as_str()) {
+                    report.errors.push(format!("{}: '{{{}}}' refers to a variable that {}", label, name, undefined_problem(&name)));
+                }
+            }
+        }
+
+        defined.insert(&var.name);
+    }
+
+    for step in &template.steps {
+        let label = format!("step '{}'", step.name);
+
+        for condition in [&step.if_condition, &step.if_not].into_iter().flatten() {
+            for name in condition.variables() {
+                if !all_variables.contains(name) {
+                    report.errors.push(format!("{}: condition refers to undefined variable '{}'", label, name));
+                }
+            }
+        }
+
+        if let Some(run) = &step.run {
+            for name in placeholders(run) {
+                if !all_variables.contains(name.as_str()) {
+                    report.errors.push(format!("{}: command uses undefined variable '{{{}}}'", label, name));
+                }
+            }
+        }
+
+        if let Some(copy) = &step.copy {
+            lint_copy(copy, template_dir, &all_variables, &label, &mut report);
+        }
+    }
+
+    report
+}
+
+fn lint_copy(copy: &CopyStep, template_dir: &Path, variables: &HashSet<&str>, label: &str, report: &mut Report) {
+    let source = match copy.source(template_dir) {
+        Ok(source) => source,
+        Err(e) => {
+            report.errors.push(format!("{}: {}", label, e));
+            return;
+        }
+    };
+    if !source.exists() {
+        report.errors.push(format!("{}: copy source '{}' does not exist", label, copy.from));
+        return;
+    }
+
+    let files = match source_files(copy, &source) {
+        Ok(files) => files,
+        Err(e) => {
+            report.errors.push(format!("{}: copy source '{}': {}", label, copy.from, e));
+            return;
+        }
+    };
+
+    // Files copied verbatim, including binary ones, have nothing to check
+    for file in files.iter().filter(|file| file.render) {
+        let Ok(content) = fs::read_to_string(&file.path) else { continue };
+        if is_binary(content.as_bytes()) {
+            continue;
+        }
+        let shown = file.path.strip_prefix(template_dir).unwrap_or(&file.path).display();
+
+        match undeclared_variables(&content) {
+            Ok(names) => {
+                for name in names.iter().filter(|name| !variables.contains(name.as_str())) {
+                    report.errors.push(format!("{}: '{}' uses undefined variable '{}'", label, shown, name));
+                }
+            },
+            Err(e) => report.errors.push(format!("{}: '{}' has a template error: {}", label, shown, e)),
+        }
+
+        // Single braces are common in code, so unknown `{name}` placeholders
+        // in files are only worth a warning
+        for name in placeholders(&content) {
+            if !variables.contains(name.as_str()) {
+                report.warnings.push(format!(
+                    "{}: '{}' contains '{{{}}}', which is not a defined variable",
+                    label, shown, name
+                ));
+            }
+        }
+    }
+}
+
+/// Names used with the `{name}` placeholder syntax, ignoring shell `${NAME}`
+/// expansions and Jinja `{{ name }}` expressions
+fn placeholders(text: &str) -> Vec<String> {
+    let re = Regex::new(r"\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap();
+    let mut names = Vec::new();
+    for captures in re.captures_iter(text) {
+        let start = captures.get(0).unwrap().start();
+        if matches!(text[..start].chars().last(), Some('$') | Some('{')) {
+            continue;
+        }
+        let name = captures[1].to_string();
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
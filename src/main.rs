@@ -1,20 +1,22 @@
-mod project;
-mod template;
-mod utils;
-mod config;
-
-use clap::Parser;
-use project::{ProjectConfig, prompt_project_config};
-use utils::check_command_exists;
-use std::io;
+use clap::{Parser, Subcommand};
+use newnew::{
+    AddSourceOptions, LintOptions, ProjectOptions, Reporter, TerminalPrompter, UpdateOptions, Value,
+    add_source, create_project, lint_templates, print_plan, prompt_project_config, update_project,
+    update_sources,
+};
 use std::path::PathBuf;
-use std::fs;
-use std::process::Command;
-use std::collections::HashMap;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    /// Config file to use instead of newnew.toml in newnew's home
+    /// ($NEWNEW_HOME, $XDG_CONFIG_HOME/newnew or ~/.config/newnew)
+    #[arg(long, global = true, value_name = "FILE")]
+    config: Option<PathBuf>,
+
     /// Install example templates
     #[arg(long)]
     examples: bool,
@@ -22,179 +24,210 @@ struct Cli {
     /// Target directory for the new project (overrides config)
     #[arg(short, long)]
     dir: Option<String>,
+
+    /// Template to use instead of choosing one interactively
+    #[arg(short, long)]
+    template: Option<String>,
+
+    /// Project name to use instead of prompting for it
+    #[arg(short, long)]
+    name: Option<String>,
+
+    /// Set a template variable, e.g. --var use_github=false (repeatable)
+    #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_var)]
+    vars: Vec<(String, String)>,
+
+    /// Load variable values from a YAML, TOML or JSON answers file
+    #[arg(long, value_name = "FILE")]
+    answers: Option<PathBuf>,
+
+    /// Accept default values for any variable not set with --var
+    #[arg(short, long)]
+    yes: bool,
+
+    /// Don't record the answers used in the generated project
+    #[arg(long)]
+    no_answers_file: bool,
+
+    /// Leave a partially created project in place if generation fails
+    #[arg(long)]
+    keep_on_failure: bool,
+
+    /// Print the steps, commands and files that would be created, without
+    /// touching the filesystem
+    #[arg(long)]
+    dry_run: bool,
 }
 
-fn main() {
-    let cli = Cli::parse();
-    let config = prompt_project_config(cli.examples, cli.dir);
-    
-    match create_project(config) {
-        Ok(_) => println!("✨ Project created successfully!"),
-        Err(e) => eprintln!("❌ Error creating project: {}", e),
-    }
+#[derive(Subcommand)]
+enum Commands {
+    /// Merge changes from the template into a project it generated
+    Update {
+        /// Project directory (defaults to the current directory)
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+
+        /// Set a variable the template has gained since generation (repeatable)
+        #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_var)]
+        vars: Vec<(String, String)>,
+
+        /// Accept default values for new variables instead of prompting
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Check templates for errors
+    Lint {
+        /// Template name or path to a template file (defaults to all templates)
+        template: Option<String>,
+
+        /// Templates directory to lint instead of the template roots
+        #[arg(short, long)]
+        dir: Option<PathBuf>,
+    },
+
+    /// Manage templates installed from git repositories
+    Template {
+        #[command(subcommand)]
+        command: TemplateCommands,
+    },
 }
 
-fn create_project(config: ProjectConfig) -> io::Result<()> {
-    // Convert ~ to absolute home directory path
-    let base_path = if config.base_path.starts_with('~') {
-        dirs::home_dir()
-            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Could not find home directory"))?
-            .join(config.base_path.strip_prefix("~/").unwrap_or(&config.base_path))
-    } else {
-        PathBuf::from(config.base_path)
-    };
-    
-    // Create base directory if it doesn't exist
-    fs::create_dir_all(&base_path)?;
-    
-    let project_path = base_path.join(&config.name);
-    fs::create_dir_all(&project_path)?;  // Create project directory immediately
-
-    // Create a new variables HashMap with project_dir added
-    let mut variables = config.variables.clone();
-    variables.insert("project_dir".to_string(), project_path.to_string_lossy().into_owned());
-
-    // Process steps in sequence
-    for step in &config.template.steps {
-        println!("⚡ Checking step: {}", step.name);
-
-        // Check if step should be run based on conditions
-        if let Some(condition) = &step.if_condition {
-            if !evaluate_condition(condition, &variables) {
-                println!("  ↪ Skipped: 'if' condition '{}' not met", condition);
-                continue;
-            }
-        }
-        if let Some(condition) = &step.if_not {
-            if evaluate_condition(condition, &variables) {
-                println!("  ↪ Skipped: 'if-not' condition '{}' not met", condition);
-                continue;
-            }
-        }
+#[derive(Subcommand)]
+enum TemplateCommands {
+    /// Clone a repository of templates and register it as a source
+    Add {
+        /// Git URL or local path of the repository
+        url: String,
+
+        /// Branch, tag or commit to use (defaults to the default branch)
+        #[arg(long = "ref", value_name = "REF")]
+        git_ref: Option<String>,
+
+        /// Name to select the templates by, as <name>/<template>
+        /// (defaults to the repository name)
+        #[arg(long)]
+        name: Option<String>,
+    },
+
+    /// Fetch new revisions of template sources
+    Update {
+        /// Source to update (defaults to all of them)
+        name: Option<String>,
+    },
+}
 
-        println!("  ▶ Executing step");
+/// Progress goes to stdout, warnings to stderr
+struct TerminalReporter;
 
-        // Check if required command exists
-        if let Some(check_cmd) = &step.check {
-            if !check_command_exists(check_cmd) {
-                return Err(io::Error::new(
-                    io::ErrorKind::NotFound,
-                    step.error.as_deref().unwrap_or(&format!("{} is not installed", check_cmd))
-                ));
-            }
-        }
+impl Reporter for TerminalReporter {
+    fn progress(&mut self, message: &str) {
+        println!("{}", message);
+    }
 
-        // Handle copy step
-        if let Some(copy) = &step.copy {
-            let template_dir = dirs::home_dir()
-                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Could not find home directory"))?
-                .join(".config")
-                .join("newnew")
-                .join("templates");
+    fn warning(&mut self, message: &str) {
+        eprintln!("⚠️  {}", message);
+    }
+}
 
-            let source = template_dir.join(&copy.from);
-            let dest = project_path.join(&copy.to);
+fn parse_var(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.to_string()))
+        },
+        _ => Err(format!("expected NAME=VALUE, got '{}'", arg)),
+    }
+}
 
-            if let Some(parent) = dest.parent() {
-                fs::create_dir_all(parent)?;
-            }
+fn main() {
+    let cli = Cli::parse();
 
-            let content = fs::read_to_string(&source)
-                .map_err(|e| io::Error::new(
-                    e.kind(),
-                    format!("Failed to read template file '{}': {}", source.display(), e)
-                ))?;
-            let expanded_content = expand_variables(&content, &variables);
-            fs::write(&dest, expanded_content)
-                .map_err(|e| io::Error::new(
-                    e.kind(),
-                    format!("Failed to write file '{}': {}", dest.display(), e)
-                ))?;
+    if let Some(config) = &cli.config {
+        if !config.is_file() {
+            eprintln!("❌ Config file '{}' not found", config.display());
+            std::process::exit(1);
         }
+    }
 
-        // Handle run command
-        if let Some(run_cmd) = &step.run {
-            // Handle multiline commands (commands with |)
-            for cmd in run_cmd.split('\n') {  // Split by newlines instead of |
-                let cmd = cmd.trim();
-                if cmd.is_empty() {
-                    continue;
-                }
-                
-                let expanded_cmd = expand_variables(cmd, &variables);
-                
-                // Split command preserving quoted strings
-                let mut parts = Vec::new();
-                let mut current = String::new();
-                let mut in_quotes = false;
-                let mut quote_char = None;
-                
-                for c in expanded_cmd.chars() {
-                    match c {
-                        '\'' | '"' => {
-                            match quote_char {
-                                None => {
-                                    quote_char = Some(c);
-                                    in_quotes = true;
-                                }
-                                Some(q) if q == c => {
-                                    quote_char = None;
-                                    in_quotes = false;
-                                }
-                                _ => current.push(c),
-                            }
-                        }
-                        ' ' if !in_quotes => {
-                            if !current.is_empty() {
-                                parts.push(current);
-                                current = String::new();
-                            }
-                        },
-                        _ => current.push(c),
-                    }
+    match cli.command {
+        Some(Commands::Update { path, vars, yes }) => {
+            let options = UpdateOptions {
+                project_path: path.unwrap_or_else(|| PathBuf::from(".")),
+                variables: vars.into_iter().map(|(name, value)| (name, Value::from(value))).collect(),
+                assume_defaults: yes,
+                config_file: cli.config,
+            };
+            match update_project(options, &mut TerminalPrompter, &mut TerminalReporter) {
+                Ok(_) => println!("✨ Project updated!"),
+                Err(e) => {
+                    eprintln!("❌ Error updating project: {}", e);
+                    std::process::exit(1);
                 }
-                if !current.is_empty() {
-                    parts.push(current);
+            }
+            return;
+        },
+        Some(Commands::Lint { template, dir }) => {
+            match lint_templates(LintOptions { template, dir, config_file: cli.config }, &mut TerminalReporter) {
+                Ok(true) => {},
+                Ok(false) => std::process::exit(1),
+                Err(e) => {
+                    eprintln!("❌ {}", e);
+                    std::process::exit(1);
                 }
-
-                let command = parts.first().unwrap();
-                let args = &parts[1..];
-
-                // Use shell to execute command to properly handle redirection
-                Command::new("sh")
-                    .arg("-c")
-                    .arg(&expanded_cmd)
-                    .current_dir(&project_path)
-                    .status()
-                    .map_err(|e| io::Error::new(
-                        io::ErrorKind::Other,
-                        format!("Failed to run command '{}': {}", expanded_cmd, e)
-                    ))?;
             }
-        }
+            return;
+        },
+        Some(Commands::Template { command }) => {
+            let result = match command {
+                TemplateCommands::Add { url, git_ref, name } => {
+                    add_source(AddSourceOptions { url, name, git_ref }, &mut TerminalReporter)
+                        .map(|source| println!("✨ Added template source '{}'", source.name))
+                },
+                TemplateCommands::Update { name } => update_sources(name.as_deref(), &mut TerminalReporter),
+            };
+            if let Err(e) = result {
+                eprintln!("❌ {}", e);
+                std::process::exit(1);
+            }
+            return;
+        },
+        None => {}
     }
 
-    Ok(())
-}
+    let options = ProjectOptions {
+        with_examples: cli.examples,
+        target_dir: cli.dir,
+        template: cli.template,
+        name: cli.name,
+        variables: cli.vars.into_iter().map(|(name, value)| (name, Value::from(value))).collect(),
+        answers_file: cli.answers,
+        assume_defaults: cli.yes,
+        skip_answers_file: cli.no_answers_file,
+        dry_run: cli.dry_run,
+        config_file: cli.config,
+    };
+
+    let config = match prompt_project_config(options, &mut TerminalPrompter, &mut TerminalReporter) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            std::process::exit(1);
+        }
+    };
 
-fn evaluate_condition(condition: &str, variables: &HashMap<String, String>) -> bool {
-    // Check if it's a negated condition
-    if condition.starts_with('!') {
-        let actual_condition = &condition[1..];
-        return variables.get(actual_condition)
-            .map(|v| v != "true")
-            .unwrap_or(true);
+    if cli.dry_run {
+        if let Err(e) = print_plan(&config, &mut TerminalReporter) {
+            eprintln!("❌ {}", e);
+            std::process::exit(1);
+        }
+        return;
     }
-    
-    variables.get(condition)
-        .map(|v| v == "true")
-        .unwrap_or(false)
-}
 
-fn expand_variables(text: &str, variables: &HashMap<String, String>) -> String {
-    let mut result = text.to_string();
-    for (key, value) in variables {
-        result = result.replace(&format!("{{{}}}", key), value);
+    match create_project(&config, cli.keep_on_failure, &mut TerminalReporter) {
+        Ok(_) => println!("✨ Project created successfully!"),
+        Err(e) => {
+            eprintln!("❌ Error creating project: {}", e);
+            std::process::exit(1);
+        }
     }
-    result
 }
@@ -0,0 +1,133 @@
+use std::env;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// Directory newnew keeps its config, templates and template sources in:
+/// `$NEWNEW_HOME` if set, otherwise `$XDG_CONFIG_HOME/newnew`, otherwise
+/// `~/.config/newnew`
+pub fn newnew_home() -> io::Result<PathBuf> {
+    if let Some(dir) = env::var_os("NEWNEW_HOME").filter(|dir| !dir.is_empty()) {
+        return expand_path(Path::new(&dir));
+    }
+
+    // The XDG spec says relative values are invalid and should be ignored
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute());
+    match config_home {
+        Some(dir) => Ok(dir.join("newnew")),
+        None => Ok(home_dir()?.join(".config").join("newnew")),
+    }
+}
+
+/// The config file used unless another one is given: `newnew.toml` in
+/// newnew's home
+pub fn config_file() -> io::Result<PathBuf> {
+    Ok(newnew_home()?.join("newnew.toml"))
+}
+
+/// The user's own templates
+pub fn templates_dir() -> io::Result<PathBuf> {
+    Ok(newnew_home()?.join("templates"))
+}
+
+/// Where template sources are cloned to
+pub fn sources_dir() -> io::Result<PathBuf> {
+    Ok(newnew_home()?.join("sources"))
+}
+
+/// The registry of template sources
+pub fn sources_file() -> io::Result<PathBuf> {
+    Ok(newnew_home()?.join("sources.toml"))
+}
+
+/// Whether `path` stays inside the directory it is joined onto: relative,
+/// with no `..`, root or drive prefix
+pub(crate) fn is_inside(path: &Path) -> bool {
+    path.components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+fn home_dir() -> io::Result<PathBuf> {
+    dirs::home_dir().ok_or_else(|| io::Error::new(
+        io::ErrorKind::NotFound,
+        "Could not determine home directory"
+    ))
+}
+
+/// Expand a leading `~` to the home directory and `$VAR` or `${VAR}` to the
+/// value of the environment variable. Referring to a variable that isn't
+/// set is an error rather than silently producing a different path.
+pub fn expand_path(path: &Path) -> io::Result<PathBuf> {
+    let text = path.to_string_lossy();
+    if !text.starts_with('~') && !text.contains('$') {
+        // Nothing to expand, keep the path as is even if it isn't UTF-8
+        return Ok(path.to_path_buf());
+    }
+
+    let invalid = |message: String| io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("Invalid path '{}': {}", text, message)
+    );
+
+    let mut expanded = String::new();
+    let mut rest: &str = &text;
+
+    if rest == "~" || rest.starts_with("~/") || rest.starts_with("~\\") {
+        expanded.push_str(&home_dir()?.to_string_lossy());
+        rest = &rest[1..];
+    }
+
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        let (name, len) = if let Some(braced) = after.strip_prefix('{') {
+            let end = braced.find('}').ok_or_else(|| invalid("missing '}'".to_string()))?;
+            (&braced[..end], end + 2)
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], end)
+        };
+
+        if name.is_empty() {
+            // A lone `$` isn't a reference, keep it
+            expanded.push('$');
+            rest = after;
+            continue;
+        }
+
+        let value = env::var(name)
+            .map_err(|_| invalid(format!("environment variable '{}' is not set", name)))?;
+        expanded.push_str(&value);
+        rest = &after[len..];
+    }
+    expanded.push_str(rest);
+
+    Ok(PathBuf::from(expanded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_home_and_variables() {
+        let home = dirs::home_dir().unwrap();
+        env::set_var("NEWNEW_TEST_DIR", "/srv/work");
+
+        assert_eq!(expand_path(Path::new("~")).unwrap(), home);
+        assert_eq!(expand_path(Path::new("~/Dev")).unwrap(), home.join("Dev"));
+        assert_eq!(expand_path(Path::new("$NEWNEW_TEST_DIR/a")).unwrap(), PathBuf::from("/srv/work/a"));
+        assert_eq!(expand_path(Path::new("${NEWNEW_TEST_DIR}_b/c")).unwrap(), PathBuf::from("/srv/work_b/c"));
+        assert_eq!(expand_path(Path::new("/plain/~user/$")).unwrap(), PathBuf::from("/plain/~user/$"));
+    }
+
+    #[test]
+    fn unset_variables_are_errors() {
+        for path in ["$NEWNEW_TEST_UNSET/x", "${NEWNEW_TEST_UNSET}", "${NEWNEW_TEST_DIR"] {
+            assert!(expand_path(Path::new(path)).is_err(), "{} should not expand", path);
+        }
+    }
+}
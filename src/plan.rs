@@ -0,0 +1,97 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use crate::copy::render_copy;
+use crate::project::{ProjectConfig, step_skip_reason};
+use crate::report::Reporter;
+use crate::template::VariableType;
+
+/// Report what generating the project would do: the resolved variables,
+/// which steps run, their fully expanded commands and the files copy steps
+/// would produce. Nothing is written and no commands are executed.
+pub fn print_plan(config: &ProjectConfig, reporter: &mut dyn Reporter) -> io::Result<()> {
+    let project_path = config.project_path()?;
+
+    reporter.progress(&format!("📝 Dry run: '{}' from template '{}'", config.name, config.template_name));
+    reporter.progress(&format!("   Target: {}", project_path.display()));
+    if project_path.exists() && (!project_path.is_dir() || fs::read_dir(&project_path)?.next().is_some()) {
+        reporter.progress("   ⚠️  Target already exists and is not empty; generation would fail");
+    }
+
+    let context = config.context(&project_path);
+    let variables = context.variables();
+
+    reporter.progress("\n🔧 Variables:");
+    let mut names: Vec<&String> = variables.keys().collect();
+    names.sort();
+    for name in names {
+        let is_password = config.template.variables
+            .iter()
+            .any(|var| &var.name == name && var.type_ == VariableType::Password);
+        if is_password {
+            reporter.progress(&format!("   {} = ********", name));
+        } else {
+            reporter.progress(&format!("   {} = {}", name, variables[name]));
+        }
+    }
+
+    reporter.progress("\n⚡ Steps:");
+    let mut files = BTreeSet::new();
+    for step in &config.template.steps {
+        if let Some(reason) = step_skip_reason(step, &context) {
+            reporter.progress(&format!("   ↪ {} (skipped: {})", step.name, reason));
+            continue;
+        }
+
+        reporter.progress(&format!("   ▶ {}", step.name));
+
+        if let Some(check_cmd) = &step.check {
+            reporter.progress(&format!("       requires: {}", check_cmd));
+        }
+
+        if let Some(copy) = &step.copy {
+            for file in render_copy(copy, &config.template.dir, &context)? {
+                reporter.progress(&format!("       copy: {} -> {}", copy.from, file.key()));
+                files.insert(file.path);
+            }
+        }
+
+        if let Some(run_cmd) = &step.run {
+            for cmd in run_cmd.lines().map(str::trim).filter(|cmd| !cmd.is_empty()) {
+                reporter.progress(&format!("       $ {}", context.expand(cmd)));
+            }
+        }
+    }
+
+    if let Some(answers_file) = &config.answers_file {
+        files.insert(PathBuf::from(answers_file));
+    }
+
+    reporter.progress("\n📁 Files (not counting output of run commands):");
+    reporter.progress(&format!("   {}/", config.name));
+    print_tree(&files, reporter);
+
+    Ok(())
+}
+
+/// Report sorted relative paths as an indented tree
+fn print_tree(files: &BTreeSet<PathBuf>, reporter: &mut dyn Reporter) {
+    let mut printed_dirs = BTreeSet::new();
+    for file in files {
+        let mut dir = PathBuf::new();
+        let components: Vec<_> = file.components().collect();
+        for (depth, component) in components.iter().enumerate() {
+            let indent = "  ".repeat(depth + 2);
+            let name = component.as_os_str().to_string_lossy();
+            if depth + 1 == components.len() {
+                reporter.progress(&format!(" {}{}", indent, name));
+            } else {
+                dir.push(component);
+                if printed_dirs.insert(dir.clone()) {
+                    reporter.progress(&format!(" {}{}/", indent, name));
+                }
+            }
+        }
+    }
+}
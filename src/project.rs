@@ -1,7 +1,17 @@
 use std::collections::HashMap;
-use crate::utils::{prompt_input, prompt_select, prompt_confirm, prompt_multiselect};
+use std::io;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use crate::answers::{Answers, load_answers};
+use crate::context::Context;
+use crate::prompt::Prompter;
+use crate::report::Reporter;
+use crate::utils::{capture_command, prompt_until_valid};
+use crate::builtins::context_variables;
 use crate::config::Config;
-use crate::template::{Template, load_templates, copy_example_templates_if_needed};
+use crate::paths::{expand_path, newnew_home};
+use crate::value::Value;
+use crate::template::{Step, Template, TemplateVariable, VariableType, load_templates, copy_example_templates_if_needed};
 
 #[derive(Debug)]
 pub struct ProjectConfig {
@@ -9,133 +19,458 @@ pub struct ProjectConfig {
     pub template_name: String,
     pub template: Template,
     pub base_path: String,
-    pub variables: HashMap<String, String>,
+    pub variables: HashMap<String, Value>,
+    /// Values newnew provides itself, such as the date and git user
+    pub builtins: HashMap<String, Value>,
+    /// File name to record the answers under in the project, if enabled
+    pub answers_file: Option<String>,
 }
 
-pub fn prompt_project_config(with_examples: bool, target_dir: Option<String>) -> ProjectConfig {
+impl ProjectConfig {
+    /// Directory the project is generated in, with `~` and `$VAR` expanded
+    pub fn project_path(&self) -> io::Result<PathBuf> {
+        Ok(expand_path(Path::new(&self.base_path))?.join(&self.name))
+    }
+
+    /// The context steps are executed with: the resolved variables plus the
+    /// ones newnew provides itself (the built-ins, variants of the project
+    /// name and the project directory). Template variables take precedence
+    /// over built-ins of the same name.
+    pub fn context(&self, project_path: &Path) -> Context {
+        let mut context = Context::new(self.builtins.clone());
+        context.insert_project_name(&self.name);
+        for (name, value) in &self.variables {
+            context.insert(name.clone(), value.clone());
+        }
+        context.insert("project_dir", Value::from(project_path.to_string_lossy().into_owned()));
+        context
+    }
+}
+
+
+/// Values supplied up front (e.g. from command line flags) that replace the
+/// corresponding interactive prompts.
+#[derive(Debug, Default)]
+pub struct ProjectOptions {
+    pub with_examples: bool,
+    pub target_dir: Option<String>,
+    pub template: Option<String>,
+    pub name: Option<String>,
+    pub variables: HashMap<String, Value>,
+    /// File with pre-filled answers; values in `variables` take precedence
+    pub answers_file: Option<PathBuf>,
+    /// Accept each variable's default instead of prompting for it
+    pub assume_defaults: bool,
+    /// Don't record the answers in the generated project
+    pub skip_answers_file: bool,
+    /// Only planning the generation: leave the filesystem as it is
+    pub dry_run: bool,
+    /// Config file to use instead of `newnew.toml` in newnew's home
+    pub config_file: Option<PathBuf>,
+}
+
+/// Work out everything needed to generate a project, asking `prompter` for
+/// whatever wasn't supplied in `options`. Problems that don't stop it, like
+/// an unreadable config file, go to `reporter`.
+pub fn prompt_project_config(
+    options: ProjectOptions,
+    prompter: &mut dyn Prompter,
+    reporter: &mut dyn Reporter,
+) -> io::Result<ProjectConfig> {
     // Load config
-    let config = match Config::load() {
+    let config_file = options.config_file.as_deref();
+    let config = match Config::load(config_file) {
         Ok(config) => config,
         Err(e) => {
-            eprintln!("⚠️  Failed to load config: {}", e);
-            eprintln!("   Using default configuration.");
+            reporter.warning(&format!("Failed to load config: {}\n   Using default configuration.", e));
             Config::default()
         }
     };
-    
+
+    // A plan leaves no trace, not even newnew's own files
+    if !options.dry_run {
+        if let Err(e) = Config::create_default_home(config_file) {
+            reporter.warning(&format!("Failed to set up '{}': {}", newnew_home()?.display(), e));
+        }
+    }
+
     // Copy example templates if flag is set
-    if let Err(e) = copy_example_templates_if_needed(with_examples) {
-        eprintln!("⚠️  Failed to copy example templates: {}", e);
+    if let Err(e) = copy_example_templates_if_needed(options.with_examples && !options.dry_run, reporter) {
+        reporter.warning(&format!("Failed to copy example templates: {}", e));
     }
-    
+
     // Load templates
-    let templates = match load_templates() {
-        Ok(templates) => templates,
-        Err(e) => {
-            eprintln!("❌ {}", e);
-            std::process::exit(1);
-        }
+    let templates = load_templates(config_file, reporter)?;
+
+    // Values from the answers file, overridden by ones given explicitly
+    let answers = match &options.answers_file {
+        Some(path) => load_answers(path)?,
+        None => Answers::default(),
     };
-    
-    // Create formatted template options
-    let template_options: Vec<String> = templates
-        .iter()
-        .map(|(name, template)| format!("{} {} {}", template.emoji, name, template.description))
-        .collect();
+    let mut supplied = answers.variables;
+    supplied.extend(options.variables);
+    let supplied_name = supplied.remove("project_name").map(|name| name.to_string());
 
-    if template_options.is_empty() {
-        eprintln!("No templates found. Try running with --examples to install example templates.");
-        std::process::exit(1);
-    }
+    let template_name = match options.template.or(answers.template) {
+        Some(name) => {
+            if !templates.contains_key(&name) {
+                let mut available: Vec<&str> = templates.keys().map(String::as_str).collect();
+                available.sort();
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("Template '{}' not found. Available templates: {}", name, available.join(", "))
+                ));
+            }
+            name
+        },
+        None if options.assume_defaults => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "No template selected. Pass --template <name> when using --yes."
+            ));
+        },
+        None => {
+            // Create formatted template options
+            let mut names: Vec<&String> = templates.keys().collect();
+            names.sort();
+            let template_options: Vec<String> = names
+                .iter()
+                .map(|name| {
+                    let template = &templates[*name];
+                    format!("{} {} {} ({})", template.emoji, name, template.description, template.root)
+                })
+                .collect();
 
-    // Get template choice using select box
-    let selected_template = prompt_select("Choose template", &template_options);
-    let template_name = selected_template.split_whitespace().nth(1).unwrap().to_string();
+            // Get template choice using select box
+            let selected = prompter.select("Choose template", &template_options)?;
+            names[selected].clone()
+        }
+    };
     let template = templates.get(&template_name).unwrap().clone();
-    
+
+    // Values that are required but were neither supplied nor defaulted
+    let mut missing = Vec::new();
+
     // Get project name
-    let name = prompt_input("Project name");
-    
-    // Collect variables from prompts
+    let name_pattern = match &config.settings.project_name_pattern {
+        Some(pattern) => Some((
+            pattern.as_str(),
+            Regex::new(&format!("^(?:{})$", pattern))
+                .map_err(|e| io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Invalid project_name_pattern in config: {}", e)
+                ))?,
+        )),
+        None => None,
+    };
+    let check_name = |name: String| validate_project_name(&name, name_pattern.as_ref()).map(|_| name);
+    let name = match options.name.or(supplied_name) {
+        Some(name) => check_name(name).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?,
+        None if options.assume_defaults => {
+            missing.push("project_name".to_string());
+            String::new()
+        },
+        None => prompt_until_valid(|| prompter.input("Project name"), check_name, reporter)?,
+    };
+
+    let prompter = if options.assume_defaults { None } else { Some(prompter) };
+    let resolved = resolve_variables(&template_name, &template, &name, supplied, prompter, options.dry_run, reporter)?;
+    missing.extend(resolved.missing);
+    if !missing.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Missing values for required variables: {}. Provide them with --name and --var <name>=<value>.",
+                missing.join(", ")
+            )
+        ));
+    }
+
+    Ok(ProjectConfig {
+        name,
+        template_name,
+        template,
+        base_path: options.target_dir.unwrap_or_else(|| config.settings.projects_dir
+            .to_str()
+            .expect("Invalid path")
+            .to_string()),
+        variables: resolved.variables,
+        builtins: resolved.builtins,
+        answers_file: if options.skip_answers_file {
+            None
+        } else {
+            Some(config.settings.answers_file)
+        },
+    })
+}
+
+/// The outcome of resolving a template's variables
+pub(crate) struct ResolvedVariables {
+    pub variables: HashMap<String, Value>,
+    pub builtins: HashMap<String, Value>,
+    /// Variables that were neither supplied nor have a default
+    pub missing: Vec<String>,
+}
+
+/// Resolve the values of a template's variables in order, skipping ones
+/// whose conditions aren't met. Values are taken from `supplied`, computed,
+/// asked for with `prompter`, or, without a prompter, set to their defaults.
+/// When only planning the generation, `command`s aren't run; the variables
+/// get a placeholder showing the command instead.
+pub(crate) fn resolve_variables(
+    template_name: &str,
+    template: &Template,
+    name: &str,
+    supplied: HashMap<String, Value>,
+    mut prompter: Option<&mut dyn Prompter>,
+    plan: bool,
+    reporter: &mut dyn Reporter,
+) -> io::Result<ResolvedVariables> {
+    // The context additionally holds the built-ins, so conditions and
+    // computed values can use them
+    let builtins = context_variables(template_name, plan);
+    let mut context = Context::new(builtins.clone());
+    context.insert_project_name(name);
     let mut variables = HashMap::new();
-    variables.insert("project_name".to_string(), name.clone());
-    
+    variables.insert("project_name".to_string(), Value::from(name));
+    let mut missing = Vec::new();
+
+    // Convert supplied values to each variable's type
+    let mut invalid = Vec::new();
+    let mut parsed = HashMap::new();
+    for (key, value) in supplied {
+        match template.variables.iter().find(|var| var.name == key) {
+            Some(var) => match var.parse_value(value) {
+                Ok(value) => {
+                    parsed.insert(key, value);
+                },
+                Err(e) => invalid.push(e),
+            },
+            None => {
+                reporter.warning(&format!("Ignoring '{}': template '{}' has no such variable", key, template_name));
+            }
+        }
+    }
+    if !invalid.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, invalid.join("\n")));
+    }
+    let supplied = parsed;
+
     // Process variables in order
     for var in &template.variables {
         // Check both if and if-not conditions
         if let Some(condition) = &var.if_condition {
-            if !evaluate_condition(condition, &variables) {
-                println!("↪ Skipping variable '{}': condition '{}' not met", var.name, condition);
+            if !context.evaluate(condition) {
+                reporter.progress(&format!("↪ Skipping variable '{}': condition '{}' not met", var.name, condition));
                 continue;
             }
         }
         if let Some(condition) = &var.if_not {
-            if evaluate_condition(condition, &variables) {
-                println!("↪ Skipping variable '{}': if-not condition '{}' not met", var.name, condition);
+            if context.evaluate(condition) {
+                reporter.progress(&format!("↪ Skipping variable '{}': if-not condition '{}' not met", var.name, condition));
                 continue;
             }
         }
 
-        let value = match var.type_.as_deref() {
-            Some("boolean") => {
-                let default = var.default.as_deref().unwrap_or("false") == "true";
-                prompt_confirm(&var.prompt, default).to_string()
+        let value = if let Some(value) = supplied.get(&var.name) {
+            value.clone()
+        } else if let Some(value) = computed_value(var, &context, plan, reporter).map_err(|e| variable_error(var, e))? {
+            value
+        } else {
+            let default = variable_default(var, &context, plan, reporter).map_err(|e| variable_error(var, e))?;
+            match prompter.as_deref_mut() {
+                Some(prompter) => prompt_variable(var, default, prompter, reporter)?,
+                None => match default.or_else(|| fallback_default(var)) {
+                    Some(value) => value,
+                    None => {
+                        missing.push(var.name.clone());
+                        continue;
+                    }
+                },
+            }
+        };
+        context.insert(var.name.clone(), value.clone());
+        variables.insert(var.name.clone(), value);
+    }
+
+    Ok(ResolvedVariables {
+        variables,
+        builtins,
+        missing,
+    })
+}
+
+/// The value of a variable that isn't prompted for: computed from its
+/// `value` expression, or the output of its `command` if it has no prompt.
+/// A failing command falls back to the `default`, if there is one.
+fn computed_value(
+    var: &TemplateVariable,
+    context: &Context,
+    plan: bool,
+    reporter: &mut dyn Reporter,
+) -> io::Result<Option<Value>> {
+    if let Some(expression) = &var.value {
+        let raw = context
+            .render(expression)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Failed to compute value: {}", e)))?;
+        return var.parse_value(Value::from(raw))
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e));
+    }
+
+    let (Some(command), true) = (&var.command, var.prompt.is_empty()) else {
+        return Ok(None);
+    };
+    let command = context.expand(command);
+    match command_value(var, &command, plan) {
+        Ok(value) => Ok(Some(value)),
+        Err(e) => match template_default(var)? {
+            Some(default) => {
+                reporter.warning(&format!("Using the default for '{}' because '{}' failed: {}", var.name, command, e));
+                Ok(Some(default))
             },
-            Some("multiselect") => {
-                if let Some(options) = &var.options {
-                    let selections = prompt_multiselect(&var.prompt, options);
-                    selections.join(",")
-                } else {
-                    prompt_input(&var.prompt)
-                }
+            None => Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        },
+    }
+}
+
+/// The default offered for a prompted variable: the output of its `command`
+/// if that succeeds, otherwise the template's `default`
+fn variable_default(
+    var: &TemplateVariable,
+    context: &Context,
+    plan: bool,
+    reporter: &mut dyn Reporter,
+) -> io::Result<Option<Value>> {
+    if let Some(command) = &var.command {
+        let command = context.expand(command);
+        match command_value(var, &command, plan) {
+            Ok(value) => return Ok(Some(value)),
+            Err(e) => reporter.warning(
+                &format!("Not using the output of '{}' as default for '{}': {}", command, var.name, e)
+            ),
+        }
+    }
+
+    template_default(var)
+}
+
+/// The output of a variable's expanded `command`, as the variable's type
+fn command_value(var: &TemplateVariable, command: &str, plan: bool) -> Result<Value, String> {
+    if plan {
+        return Ok(planned_output(command));
+    }
+    capture_command(command)
+        .map_err(|e| e.to_string())
+        .and_then(|output| var.parse_value(Value::from(output)))
+}
+
+fn template_default(var: &TemplateVariable) -> io::Result<Option<Value>> {
+    var.default_value()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid default: {}", e)))
+}
+
+/// Stands in for the output of a `command` while planning, where nothing is
+/// executed
+pub(crate) fn planned_output(command: &str) -> Value {
+    Value::String(format!("<output of '{}'>", command))
+}
+
+fn prompt_variable(
+    var: &TemplateVariable,
+    default: Option<Value>,
+    prompter: &mut dyn Prompter,
+    reporter: &mut dyn Reporter,
+) -> io::Result<Value> {
+    let prompt = || -> io::Result<Value> {
+        let value = match (var.type_, &var.options) {
+            (VariableType::Boolean, _) => {
+                let default = matches!(default, Some(Value::Bool(true)));
+                Value::Bool(prompter.confirm(&var.prompt, default)?)
             },
-            Some("select") => {
-                if let Some(options) = &var.options {
-                    prompt_select(&var.prompt, options)
-                } else {
-                    prompt_input(&var.prompt)
-                }
+            (VariableType::Multiselect, Some(options)) => {
+                let defaults = match &default {
+                    Some(Value::List(items)) => items.clone(),
+                    _ => Vec::new(),
+                };
+                Value::List(prompter.multiselect(&var.prompt, options, &defaults)?)
+            },
+            (VariableType::Select, Some(options)) => {
+                Value::String(options[prompter.select(&var.prompt, options)?].clone())
             },
+            (VariableType::Password, _) => Value::String(prompter.password(&var.prompt)?),
             _ => {
-                let default = var.default.as_deref().unwrap_or("");
-                let prompt = if default.is_empty() {
-                    var.prompt.clone()
-                } else {
-                    format!("{} (default: {})", var.prompt, default)
+                let prompt = match &default {
+                    Some(default) => format!("{} (default: {})", var.prompt, default),
+                    None => var.prompt.clone(),
                 };
-                let response = prompt_input(&prompt);
-                if response.is_empty() {
-                    default.to_string()
-                } else {
-                    response
+                match prompter.input(&prompt)? {
+                    response if response.is_empty() => default.clone().unwrap_or(Value::String(response)),
+                    response => Value::String(response),
                 }
             }
         };
-        variables.insert(var.name.clone(), value);
+        Ok(value)
+    };
+    prompt_until_valid(prompt, |value| var.parse_value(value), reporter)
+}
+
+/// The value a variable without a default takes when the user accepts
+/// defaults without being prompted. Mirrors what the interactive prompts
+/// preselect; `None` means the variable has no sensible default and must be
+/// supplied.
+fn fallback_default(var: &TemplateVariable) -> Option<Value> {
+    match var.type_ {
+        VariableType::Boolean => Some(Value::Bool(false)),
+        VariableType::Multiselect if var.options.is_some() => Some(Value::List(Vec::new())),
+        VariableType::Select => var.options.as_ref()
+            .and_then(|options| options.first().cloned())
+            .map(Value::String),
+        _ => None,
     }
+}
 
-    ProjectConfig {
-        name,
-        template_name,
-        template,
-        base_path: target_dir.unwrap_or_else(|| config.settings.projects_dir
-            .to_str()
-            .expect("Invalid path")
-            .to_string()),
-        variables,
+/// Why a step won't run with the given variables, or `None` if it will
+pub fn step_skip_reason(step: &Step, context: &Context) -> Option<String> {
+    if let Some(condition) = &step.if_condition {
+        if !context.evaluate(condition) {
+            return Some(format!("'if' condition '{}' not met", condition));
+        }
     }
+    if let Some(condition) = &step.if_not {
+        if context.evaluate(condition) {
+            return Some(format!("'if-not' condition '{}' not met", condition));
+        }
+    }
+    None
 }
 
-fn evaluate_condition(condition: &str, variables: &HashMap<String, String>) -> bool {
-    // Check if it's a negated condition
-    if condition.starts_with('!') {
-        let actual_condition = &condition[1..];
-        return variables.get(actual_condition)
-            .map(|v| v != "true")
-            .unwrap_or(true);
+/// Project names become a directory below the projects directory, so they
+/// must be a single, non-empty path component. They also end up unquoted in
+/// `run` commands such as `cargo init {project_name}`, so whitespace is
+/// rejected too. Anything stricter, like lowercase only, is up to
+/// `project_name_pattern` in the config.
+pub(crate) fn validate_project_name(name: &str, pattern: Option<&(&str, Regex)>) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Project name must not be empty".to_string());
+    }
+    if name.contains(['/', '\\']) || name == "." || name == ".." {
+        return Err(format!("Invalid project name '{}': must not contain path separators or be '.' or '..'", name));
     }
-    
-    variables.get(condition)
-        .map(|v| v == "true")
-        .unwrap_or(false)
-} 
\ No newline at end of file
+    if name.contains(char::is_whitespace) {
+        return Err(format!("Invalid project name '{}': must not contain whitespace", name));
+    }
+    if let Some((pattern, re)) = pattern {
+        if !re.is_match(name) {
+            return Err(format!(
+                "Invalid project name '{}': must match the pattern '{}' from the config",
+                name, pattern
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn variable_error(var: &TemplateVariable, e: io::Error) -> io::Error {
+    io::Error::new(e.kind(), format!("Variable '{}': {}", var.name, e))
+}
@@ -0,0 +1,134 @@
+use std::collections::VecDeque;
+use std::io;
+use crate::utils::{prompt_confirm, prompt_input, prompt_multiselect, prompt_password, prompt_select};
+
+/// Where answers to questions come from. The CLI asks on the terminal;
+/// other frontends can answer from a form, a queue of scripted answers, etc.
+pub trait Prompter {
+    /// Free text; an empty answer means "use the default"
+    fn input(&mut self, prompt: &str) -> io::Result<String>;
+    /// Index of the chosen option
+    fn select(&mut self, prompt: &str, options: &[String]) -> io::Result<usize>;
+    fn confirm(&mut self, prompt: &str, default: bool) -> io::Result<bool>;
+    /// Any of `options`, with `defaults` preselected
+    fn multiselect(&mut self, prompt: &str, options: &[String], defaults: &[String]) -> io::Result<Vec<String>>;
+    /// Free text that isn't echoed
+    fn password(&mut self, prompt: &str) -> io::Result<String>;
+}
+
+/// Asks on the terminal
+pub struct TerminalPrompter;
+
+impl Prompter for TerminalPrompter {
+    fn input(&mut self, prompt: &str) -> io::Result<String> {
+        prompt_input(prompt)
+    }
+
+    fn select(&mut self, prompt: &str, options: &[String]) -> io::Result<usize> {
+        prompt_select(prompt, options)
+    }
+
+    fn confirm(&mut self, prompt: &str, default: bool) -> io::Result<bool> {
+        prompt_confirm(prompt, default)
+    }
+
+    fn multiselect(&mut self, prompt: &str, options: &[String], defaults: &[String]) -> io::Result<Vec<String>> {
+        prompt_multiselect(prompt, options, defaults)
+    }
+
+    fn password(&mut self, prompt: &str) -> io::Result<String> {
+        prompt_password(prompt)
+    }
+}
+
+/// Answers questions from a fixed list, in order, for tests and other
+/// non-interactive runs. Each question takes the next answer:
+///
+/// - `input`/`password`: the answer as is, `""` for the default
+/// - `select`: one of the options, or for the template picker, a template's
+///   id (e.g. `rust` for `🦀 rust A Rust project (user)`)
+/// - `confirm`: `y`/`yes`/`true` or `n`/`no`/`false`, `""` for the default
+/// - `multiselect`: comma-separated options, `""` for the defaults
+///
+/// Running out of answers, or an answer that doesn't fit the question,
+/// is an error.
+pub struct ScriptedPrompter {
+    answers: VecDeque<String>,
+}
+
+impl ScriptedPrompter {
+    pub fn new<I, S>(answers: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        ScriptedPrompter {
+            answers: answers.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Answers that haven't been used yet
+    pub fn remaining(&self) -> usize {
+        self.answers.len()
+    }
+
+    fn next(&mut self, prompt: &str) -> io::Result<String> {
+        self.answers.pop_front().ok_or_else(|| io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("No scripted answer left for '{}'", prompt)
+        ))
+    }
+}
+
+/// The id in a template picker option, which follows the template's emoji
+fn template_id(option: &str) -> Option<&str> {
+    option.split_whitespace().nth(1)
+}
+
+fn invalid_answer(prompt: &str, answer: &str, expected: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("Scripted answer '{}' for '{}' is not {}", answer, prompt, expected)
+    )
+}
+
+impl Prompter for ScriptedPrompter {
+    fn input(&mut self, prompt: &str) -> io::Result<String> {
+        self.next(prompt)
+    }
+
+    fn select(&mut self, prompt: &str, options: &[String]) -> io::Result<usize> {
+        let answer = self.next(prompt)?;
+        options.iter()
+            .position(|option| *option == answer)
+            .or_else(|| options.iter().position(|option| template_id(option) == Some(answer.as_str())))
+            .ok_or_else(|| invalid_answer(prompt, &answer, &format!("one of {}", options.join(", "))))
+    }
+
+    fn confirm(&mut self, prompt: &str, default: bool) -> io::Result<bool> {
+        let answer = self.next(prompt)?;
+        match answer.to_lowercase().as_str() {
+            "" => Ok(default),
+            "y" | "yes" | "true" => Ok(true),
+            "n" | "no" | "false" => Ok(false),
+            _ => Err(invalid_answer(prompt, &answer, "yes or no")),
+        }
+    }
+
+    fn multiselect(&mut self, prompt: &str, options: &[String], defaults: &[String]) -> io::Result<Vec<String>> {
+        let answer = self.next(prompt)?;
+        if answer.is_empty() {
+            return Ok(defaults.to_vec());
+        }
+
+        let selected: Vec<String> = answer.split(',').map(|item| item.trim().to_string()).collect();
+        match selected.iter().find(|item| !options.contains(item)) {
+            Some(item) => Err(invalid_answer(prompt, item, &format!("any of {}", options.join(", ")))),
+            None => Ok(selected),
+        }
+    }
+
+    fn password(&mut self, prompt: &str) -> io::Result<String> {
+        self.next(prompt)
+    }
+}
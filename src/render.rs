@@ -0,0 +1,29 @@
+use heck::{ToKebabCase, ToLowerCamelCase, ToShoutySnakeCase, ToSnakeCase, ToTitleCase, ToUpperCamelCase};
+use minijinja::Environment;
+
+/// Variables a file's contents refer to through the template engine, not
+/// counting engine globals such as `range`. Fails on syntax errors.
+pub fn undeclared_variables(content: &str) -> Result<Vec<String>, String> {
+    let env = environment();
+    let template = env.template_from_str(content).map_err(|e| e.to_string())?;
+    let mut names: Vec<String> = template
+        .undeclared_variables(false)
+        .into_iter()
+        .filter(|name| !env.globals().any(|(global, _)| global == name))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// The template engine with newnew's filters registered
+pub fn environment() -> Environment<'static> {
+    let mut env = Environment::new();
+    env.set_keep_trailing_newline(true);
+    env.add_filter("snake_case", |s: &str| s.to_snake_case());
+    env.add_filter("kebab_case", |s: &str| s.to_kebab_case());
+    env.add_filter("camel_case", |s: &str| s.to_lower_camel_case());
+    env.add_filter("pascal_case", |s: &str| s.to_upper_camel_case());
+    env.add_filter("title_case", |s: &str| s.to_title_case());
+    env.add_filter("screaming_snake_case", |s: &str| s.to_shouty_snake_case());
+    env
+}
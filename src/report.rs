@@ -0,0 +1,18 @@
+/// Where progress and warnings go. The library never prints; the CLI writes
+/// them to the terminal, other frontends can log them, show them in a UI or
+/// drop them.
+pub trait Reporter {
+    /// What is being done, e.g. which step is running
+    fn progress(&mut self, message: &str);
+    /// Something that didn't stop the work but may need attention
+    fn warning(&mut self, message: &str);
+}
+
+/// Drops everything
+pub struct SilentReporter;
+
+impl Reporter for SilentReporter {
+    fn progress(&mut self, _message: &str) {}
+
+    fn warning(&mut self, _message: &str) {}
+}
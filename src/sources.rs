@@ -0,0 +1,276 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use crate::paths::{sources_dir, sources_file};
+use crate::report::Reporter;
+use crate::template::TemplateRoot;
+use crate::utils::{check_command_exists, command_output};
+
+/// A git repository templates are installed from. Its templates are
+/// selected as `<name>/<template>`, e.g. `acme/rust-service`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Source {
+    pub name: String,
+    /// Anything `git clone` accepts: a URL, a `file://` URL or a local path
+    pub url: String,
+    /// Branch, tag or commit to check out; the default branch if `None`
+    #[serde(rename = "ref", default, skip_serializing_if = "Option::is_none")]
+    pub git_ref: Option<String>,
+}
+
+impl Source {
+    /// Where the repository is cloned to
+    pub fn path(&self) -> io::Result<PathBuf> {
+        Ok(sources_dir()?.join(&self.name))
+    }
+
+    /// Directory holding the source's templates: `templates/` if the
+    /// repository has one, otherwise its root
+    pub fn templates_dir(&self) -> io::Result<PathBuf> {
+        let path = self.path()?;
+        let templates = path.join("templates");
+        Ok(if templates.is_dir() { templates } else { path })
+    }
+
+    /// The source's templates directory as a template root
+    pub(crate) fn root(&self) -> io::Result<TemplateRoot> {
+        Ok(TemplateRoot {
+            label: format!("source {}", self.name),
+            dir: self.templates_dir()?,
+            source: Some(self.name.clone()),
+        })
+    }
+}
+
+/// The sources registry, kept apart from `newnew.toml` so rewriting it
+/// doesn't lose the comments and formatting of the user's settings
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Registry {
+    #[serde(default)]
+    sources: Vec<Source>,
+}
+
+pub struct AddSourceOptions {
+    pub url: String,
+    /// Name to register the source as (defaults to the repository name)
+    pub name: Option<String>,
+    pub git_ref: Option<String>,
+}
+
+/// Registered template sources, in the order they were added
+pub fn load_sources() -> io::Result<Vec<Source>> {
+    let path = sources_file()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&path)?;
+    let invalid = |message: String| io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Invalid sources file '{}': {}", path.display(), message)
+    );
+    let registry = toml::from_str::<Registry>(&content).map_err(|e| invalid(e.to_string()))?;
+    for source in &registry.sources {
+        if let Some(git_ref) = &source.git_ref {
+            validate_ref(git_ref).map_err(|e| invalid(e.to_string()))?;
+        }
+    }
+    Ok(registry.sources)
+}
+
+fn save_sources(sources: &[Source]) -> io::Result<()> {
+    let path = sources_file()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let registry = Registry { sources: sources.to_vec() };
+    let content = toml::to_string(&registry).map_err(io::Error::other)?;
+    fs::write(path, content)
+}
+
+/// Clone a repository and register it as a template source
+pub fn add_source(options: AddSourceOptions, reporter: &mut dyn Reporter) -> io::Result<Source> {
+    require_git()?;
+
+    // Record local paths absolutely so updates work from any directory
+    let url = match fs::canonicalize(&options.url) {
+        Ok(path) => path.to_string_lossy().into_owned(),
+        Err(_) => options.url,
+    };
+    let name = match options.name {
+        Some(name) => name,
+        None => source_name_from_url(&url),
+    };
+    validate_source_name(&name)?;
+    if let Some(git_ref) = &options.git_ref {
+        validate_ref(git_ref)?;
+    }
+
+    let mut sources = load_sources()?;
+    if sources.iter().any(|source| source.name == name) {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("A template source named '{}' already exists. Choose another with --name.", name)
+        ));
+    }
+
+    let source = Source { name, url, git_ref: options.git_ref };
+    let path = source.path()?;
+    if path.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("'{}' already exists. Remove it or choose another name with --name.", path.display())
+        ));
+    }
+
+    reporter.progress(&format!("📥 Cloning '{}' into '{}'", source.url, path.display()));
+    if let Err(e) = clone_source(&source, &path) {
+        let _ = fs::remove_dir_all(&path);
+        return Err(e);
+    }
+
+    let templates = source.root()?.template_files(reporter)?;
+    if templates.is_empty() {
+        reporter.warning(&format!("'{}' contains no templates", source.url));
+    }
+    for template in templates {
+        reporter.progress(&format!("  📋 {}", template.id));
+    }
+
+    sources.push(source.clone());
+    save_sources(&sources)?;
+    Ok(source)
+}
+
+/// Fetch new revisions of one source, or all of them if `name` is `None`,
+/// and check out their configured ref again
+pub fn update_sources(name: Option<&str>, reporter: &mut dyn Reporter) -> io::Result<()> {
+    require_git()?;
+
+    let sources = load_sources()?;
+    let selected: Vec<&Source> = sources
+        .iter()
+        .filter(|source| name.is_none_or(|name| source.name == name))
+        .collect();
+
+    if selected.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            match name {
+                Some(name) => format!("No template source named '{}'", name),
+                None => "No template sources. Add one with 'newnew template add <url>'.".to_string(),
+            }
+        ));
+    }
+
+    for source in selected {
+        let path = source.path()?;
+        if !path.exists() {
+            reporter.progress(&format!("📥 {}: cloning '{}' again", source.name, source.url));
+            clone_source(source, &path)?;
+            continue;
+        }
+
+        let before = git(&["rev-parse", "--short", "HEAD"], Some(&path))?;
+        git(&["fetch", "--quiet", "--tags", "--force", "origin"], Some(&path))?;
+        checkout(source, &path)?;
+        let after = git(&["rev-parse", "--short", "HEAD"], Some(&path))?;
+
+        if before == after {
+            reporter.progress(&format!("✅ {}: up to date at {}", source.name, after));
+        } else {
+            reporter.progress(&format!("🔄 {}: updated {} → {}", source.name, before, after));
+        }
+    }
+
+    Ok(())
+}
+
+fn clone_source(source: &Source, path: &Path) -> io::Result<()> {
+    let destination = path.to_string_lossy();
+    // `--` keeps a URL starting with `-` from being taken as an option
+    git(&["clone", "--quiet", "--", &source.url, &destination], None)?;
+    checkout(source, path)
+}
+
+/// Check out the source's ref, preferring the remote branch of that name
+/// so updates follow it, or the remote's default branch without a ref
+fn checkout(source: &Source, path: &Path) -> io::Result<()> {
+    let revision = match &source.git_ref {
+        Some(git_ref) => {
+            let remote_branch = format!("refs/remotes/origin/{}", git_ref);
+            if git(&["rev-parse", "--verify", "--quiet", &remote_branch], Some(path)).is_ok() {
+                remote_branch
+            } else {
+                git_ref.clone()
+            }
+        },
+        None => "refs/remotes/origin/HEAD".to_string(),
+    };
+
+    git(&["checkout", "--quiet", "--detach", &revision], Some(path))
+        .map(|_| ())
+        .map_err(|e| io::Error::new(
+            e.kind(),
+            format!("Failed to check out '{}' of '{}': {}", revision, source.name, e)
+        ))
+}
+
+/// Run git with separate arguments, so URLs and paths need no quoting, and
+/// return its trimmed output
+fn git(args: &[&str], dir: Option<&Path>) -> io::Result<String> {
+    let mut command = Command::new("git");
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+
+    command_output(command.args(args), &format!("git {}", args[0]))
+}
+
+fn require_git() -> io::Result<()> {
+    if check_command_exists("git") {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::NotFound, "git is required to install templates from repositories"))
+    }
+}
+
+/// Refs are passed to git as revisions, where a leading `-` would make them
+/// options
+fn validate_ref(git_ref: &str) -> io::Result<()> {
+    if git_ref.is_empty() || git_ref.starts_with('-') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid ref '{}': refs can't be empty or start with '-'", git_ref)
+        ));
+    }
+    Ok(())
+}
+
+/// The last path component of a URL without `.git`, e.g. `rust-templates`
+/// for `https://github.com/acme/rust-templates.git`
+fn source_name_from_url(url: &str) -> String {
+    let last = url
+        .trim_end_matches(['/', '\\'])
+        .rsplit(['/', '\\', ':'])
+        .next()
+        .unwrap_or("");
+    last.strip_suffix(".git").unwrap_or(last).to_string()
+}
+
+fn validate_source_name(name: &str) -> io::Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid source name '{}': use letters, digits, '-', '_' and '.'. Choose one with --name.", name)
+        ))
+    }
+}
@@ -1,43 +1,255 @@
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::condition::Condition;
+use crate::config::Config;
+use crate::inherit::resolve_extends;
+use crate::paths::{expand_path, is_inside, templates_dir};
+use crate::report::Reporter;
+use crate::sources::load_sources;
+use crate::value::Value;
 use std::collections::HashMap;
+use std::env;
+use std::fmt;
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Template {
+    /// Name the template is selected by, i.e. its file name without `.yml`,
+    /// prefixed with `<source>/` for templates installed from a repository
+    #[serde(skip)]
+    pub id: String,
     pub name: String,
     pub description: String,
     pub emoji: String,
     #[serde(default)]
+    pub version: Option<String>,
+    /// Template whose variables and steps this one builds on
+    #[serde(default)]
+    pub extends: Option<String>,
+    #[serde(default)]
     pub variables: Vec<TemplateVariable>,
+    #[serde(default)]
     pub steps: Vec<Step>,
+    /// SHA-256 of the template file this was loaded from, and for bundles of
+    /// the files next to it
+    #[serde(skip)]
+    pub hash: String,
+    /// Directory `copy.from` is relative to: the template's bundle
+    /// directory, or the root it was loaded from for flat `.yml` files
+    #[serde(skip)]
+    pub dir: PathBuf,
+    /// Label of the template root it was loaded from, e.g. `project`
+    #[serde(skip)]
+    pub root: String,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TemplateVariable {
     pub name: String,
+    /// Question asked for the value; variables computed from a `command`
+    /// without a prompt use the command's output directly
+    #[serde(default)]
     pub prompt: String,
-    #[serde(rename = "type")]
-    pub type_: Option<String>,
-    pub default: Option<String>,
-    #[serde(rename = "if")]
-    pub if_: Option<String>,
-    pub if_condition: Option<String>,
+    #[serde(rename = "type", default)]
+    pub type_: VariableType,
+    pub default: Option<Value>,
+    #[serde(rename = "if", alias = "if_condition")]
+    pub if_condition: Option<Condition>,
     #[serde(rename = "if-not")]
-    pub if_not: Option<String>,
+    pub if_not: Option<Condition>,
     pub options: Option<Vec<String>>,
+    /// Expression the value is computed from instead of prompting, e.g.
+    /// `{project_name}-service`
+    pub value: Option<String>,
+    /// Shell command whose output is the default, or the value itself if
+    /// there is no `prompt`
+    pub command: Option<String>,
+    /// Regular expression the whole value must match
+    pub pattern: Option<String>,
+    /// Minimum length of text, or number of selected options for multiselect
+    pub min_length: Option<usize>,
+    /// Maximum length of text, or number of selected options for multiselect
+    pub max_length: Option<usize>,
+    /// Smallest accepted integer
+    pub min: Option<i64>,
+    /// Largest accepted integer
+    pub max: Option<i64>,
+    /// Shown instead of the generated message when validation fails
+    pub validate_message: Option<String>,
+}
+
+/// The kind of a template variable, deciding how it is prompted for and what
+/// values it accepts
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum VariableType {
+    #[default]
+    String,
+    Boolean,
+    Select,
+    Multiselect,
+    Integer,
+    Path,
+    Password,
+}
+
+impl fmt::Display for VariableType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            VariableType::String => "string",
+            VariableType::Boolean => "boolean",
+            VariableType::Select => "select",
+            VariableType::Multiselect => "multiselect",
+            VariableType::Integer => "integer",
+            VariableType::Path => "path",
+            VariableType::Password => "password",
+        };
+        f.write_str(name)
+    }
+}
+
+impl TemplateVariable {
+    /// Convert a value that was supplied without prompting (answers file,
+    /// `--var` or the template's `default`) to the variable's type and check
+    /// it against the variable's options. Strings are parsed, so `"true"` is
+    /// accepted for a boolean and `"a,b"` for a multiselect. The converted
+    /// value must also pass the variable's validation rules.
+    pub fn parse_value(&self, value: Value) -> Result<Value, String> {
+        let shown = self.shown(&value);
+        let invalid = |expected: &str| format!(
+            "Invalid value '{}' for '{}': expected {}",
+            shown, self.name, expected
+        );
+
+        let value = match (self.type_, &value) {
+            (VariableType::Boolean, Value::Bool(_)) => value.clone(),
+            (VariableType::Boolean, Value::String(s)) => match s.as_str() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                _ => return Err(invalid("true or false")),
+            },
+            (VariableType::Boolean, _) => return Err(invalid("true or false")),
+            (VariableType::Integer, Value::Integer(_)) => value.clone(),
+            (VariableType::Integer, Value::String(s)) => match s.trim().parse() {
+                Ok(i) => Value::Integer(i),
+                Err(_) => return Err(invalid("a whole number")),
+            },
+            (VariableType::Integer, _) => return Err(invalid("a whole number")),
+            (VariableType::Multiselect, Value::List(_)) => value.clone(),
+            (VariableType::Multiselect, Value::String(s)) => Value::List(
+                s.split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            ),
+            (VariableType::Multiselect, _) => return Err(invalid("a list")),
+            (_, Value::List(_)) => return Err(invalid("a single value")),
+            (_, Value::String(_)) => value.clone(),
+            (_, other) => Value::String(other.to_string()),
+        };
+
+        if let Some(options) = &self.options {
+            match &value {
+                Value::String(s) if self.type_ == VariableType::Select && !options.contains(s) => {
+                    return Err(invalid(&format!("one of {}", options.join(", "))));
+                },
+                Value::List(items) => {
+                    if let Some(item) = items.iter().find(|item| !options.contains(item)) {
+                        return Err(format!(
+                            "Invalid value '{}' for '{}': expected any of {}",
+                            item, self.name, options.join(", ")
+                        ));
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        self.validate(&value)?;
+        Ok(value)
+    }
+
+    /// Check a value of the variable's type against `pattern`,
+    /// `min_length`/`max_length` and `min`/`max`
+    fn validate(&self, value: &Value) -> Result<(), String> {
+        let problem = match value {
+            Value::String(s) => {
+                let length = s.chars().count();
+                let matches_pattern = self.pattern_regex()?.is_none_or(|re| re.is_match(s));
+                self.min_length
+                    .filter(|&min| length < min)
+                    .map(|min| format!("must be at least {} characters long", min))
+                    .or_else(|| self.max_length
+                        .filter(|&max| length > max)
+                        .map(|max| format!("must be at most {} characters long", max)))
+                    .or_else(|| self.pattern
+                        .as_ref()
+                        .filter(|_| !matches_pattern)
+                        .map(|pattern| format!("must match the pattern '{}'", pattern)))
+            },
+            Value::List(items) => self.min_length
+                .filter(|&min| items.len() < min)
+                .map(|min| format!("select at least {} option(s)", min))
+                .or_else(|| self.max_length
+                    .filter(|&max| items.len() > max)
+                    .map(|max| format!("select at most {} option(s)", max))),
+            Value::Integer(i) => self.min
+                .filter(|&min| *i < min)
+                .map(|min| format!("must be at least {}", min))
+                .or_else(|| self.max
+                    .filter(|&max| *i > max)
+                    .map(|max| format!("must be at most {}", max))),
+            Value::Bool(_) | Value::Float(_) => None,
+        };
+
+        match problem {
+            Some(problem) => Err(format!(
+                "Invalid value '{}' for '{}': {}",
+                self.shown(value),
+                self.name,
+                self.validate_message.as_deref().unwrap_or(&problem)
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// The compiled `pattern`, anchored so it has to match the whole value
+    pub fn pattern_regex(&self) -> Result<Option<Regex>, String> {
+        self.pattern
+            .as_ref()
+            .map(|pattern| Regex::new(&format!("^(?:{})$", pattern))
+                .map_err(|e| format!("Invalid pattern for '{}': {}", self.name, e)))
+            .transpose()
+    }
+
+    /// How a value appears in messages; passwords are never echoed
+    fn shown(&self, value: &Value) -> String {
+        match self.type_ {
+            VariableType::Password => "********".to_string(),
+            _ => value.to_string(),
+        }
+    }
+
+    /// The template's `default`, converted to the variable's type
+    pub fn default_value(&self) -> Result<Option<Value>, String> {
+        self.default
+            .clone()
+            .map(|default| self.parse_value(default))
+            .transpose()
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Step {
     pub name: String,
-    #[serde(default)]
-    pub if_: Option<String>,
-    #[serde(rename = "if")]
-    pub if_condition: Option<String>,
+    #[serde(rename = "if", alias = "if_")]
+    pub if_condition: Option<Condition>,
     #[serde(rename = "if-not")]
-    pub if_not: Option<String>,
+    pub if_not: Option<Condition>,
     #[serde(default)]
     pub run: Option<String>,
     #[serde(default)]
@@ -46,67 +258,234 @@ pub struct Step {
     pub error: Option<String>,
     #[serde(default)]
     pub copy: Option<CopyStep>,
+    /// Warn about failing commands instead of failing the generation, and
+    /// keep going with the step's remaining commands and the next steps
+    #[serde(default)]
+    pub continue_on_error: bool,
+    /// How many times to re-run a failing command before giving up
+    #[serde(default)]
+    pub retries: u32,
+    /// With `extends`, insert this step before the parent step of this name
+    #[serde(default)]
+    pub before: Option<String>,
+    /// With `extends`, insert this step after the parent step of this name
+    #[serde(default)]
+    pub after: Option<String>,
+    /// With `extends`, drop the parent step with this step's name
+    #[serde(default)]
+    pub remove: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct CopyStep {
     pub from: String,
     pub to: String,
+    /// Glob patterns of files to copy when `from` is a directory (all if empty)
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Glob patterns of files to skip when `from` is a directory
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Copy files byte for byte without rendering them
+    #[serde(default)]
+    pub raw: bool,
+    /// Glob patterns of files to copy without rendering
+    #[serde(default)]
+    pub no_render: Vec<String>,
+    /// Directory `from` is relative to when it isn't the template's own,
+    /// i.e. for steps inherited from the template it extends
+    #[serde(skip)]
+    pub dir: Option<PathBuf>,
 }
 
-pub fn load_templates() -> io::Result<HashMap<String, Template>> {
-    let template_dir = get_template_dir()?;
-    let mut templates = HashMap::new();
-    let mut had_errors = false;
+impl CopyStep {
+    /// Path of `from` below `template_dir`, or below the directory of the
+    /// template the step was inherited from. Absolute paths and `..` are
+    /// rejected so a template can't copy files from outside its directory.
+    pub fn source(&self, template_dir: &Path) -> io::Result<PathBuf> {
+        if !is_inside(Path::new(&self.from)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Copy source '{}' must be a relative path inside the template", self.from)
+            ));
+        }
+        Ok(self.dir.as_deref().unwrap_or(template_dir).join(&self.from))
+    }
+}
 
-    if !template_dir.exists() {
-        fs::create_dir_all(&template_dir)?;
+/// Name of the template definition in a bundle directory
+pub const BUNDLE_FILE: &str = "template.yml";
+
+/// System-wide templates, e.g. installed by a package
+const SYSTEM_TEMPLATE_DIR: &str = "/usr/share/newnew/templates";
+
+/// A directory templates are loaded from
+#[derive(Debug, Clone)]
+pub struct TemplateRoot {
+    /// Where the directory comes from, e.g. `project` or `user`, shown next
+    /// to each template in the picker
+    pub label: String,
+    pub dir: PathBuf,
+    /// Name templates are prefixed with, for templates from a source
+    pub source: Option<String>,
+}
+
+impl TemplateRoot {
+    pub(crate) fn new(label: &str, dir: PathBuf) -> Self {
+        TemplateRoot { label: label.to_string(), dir, source: None }
     }
 
-    for entry in fs::read_dir(template_dir)? {
-        let entry = entry?;
-        let path = entry.path();
-        
-        if path.extension().and_then(|s| s.to_str()) == Some("yml") {
-            let template_name = path.file_stem()
-                .and_then(|s| s.to_str())
-                .unwrap_or("unknown")
-                .to_string();
-
-            match fs::read_to_string(&path) {
-                Ok(content) => {
-                    match serde_yaml::from_str(&content) {
-                        Ok(template) => {
-                            templates.insert(template_name, template);
-                        },
-                        Err(e) => {
-                            had_errors = true;
-                            eprintln!("⚠️  Error parsing template '{}': {}", path.display(), e);
-                            eprintln!("   This template will be skipped. Please check the YAML format.");
-                            
-                            // If it's specifically a variables format error, show migration help
-                            if e.to_string().contains("variables: invalid type: map") {
-                                eprintln!("\nℹ️  The template format has changed. Variables should now be a sequence.");
-                                eprintln!("   Update your template from:");
-                                eprintln!("   variables:");
-                                eprintln!("     use_typescript:");
-                                eprintln!("       prompt: \"Use TypeScript?\"");
-                                eprintln!("\n   To:");
-                                eprintln!("   variables:");
-                                eprintln!("     - name: use_typescript");
-                                eprintln!("       prompt: \"Use TypeScript?\"\n");
-                            }
-                        }
-                    }
-                },
-                Err(e) => {
-                    had_errors = true;
-                    eprintln!("⚠️  Error reading template file '{}': {}", path.display(), e);
-                }
+    /// Name a template file in this root is selected by
+    fn template_id(&self, stem: &str) -> String {
+        match &self.source {
+            Some(source) => format!("{}/{}", source, stem),
+            None => stem.to_string(),
+        }
+    }
+
+    /// Templates in this root, sorted by name. A root that doesn't exist
+    /// has none.
+    pub(crate) fn template_files(&self, reporter: &mut dyn Reporter) -> io::Result<Vec<TemplateFile>> {
+        if !self.dir.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut bundles = Vec::new();
+        let mut flat = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+
+            if path.join(BUNDLE_FILE).is_file() {
+                let Some(name) = path.file_name().and_then(|s| s.to_str()) else { continue };
+                bundles.push(TemplateFile {
+                    id: self.template_id(name),
+                    path: path.join(BUNDLE_FILE),
+                    dir: path.clone(),
+                });
+            } else if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("yml") {
+                let Some(name) = path.file_stem().and_then(|s| s.to_str()) else { continue };
+                flat.push(TemplateFile {
+                    id: self.template_id(name),
+                    path: path.clone(),
+                    dir: self.dir.clone(),
+                });
             }
         }
+
+        // A flat file next to a bundle of the same name is most likely the
+        // old copy of a template that was converted
+        for file in flat {
+            match bundles.iter().find(|bundle| bundle.id == file.id) {
+                Some(bundle) => reporter.warning(&format!(
+                    "Ignoring '{}': the bundle '{}' has the same name",
+                    file.path.display(), bundle.dir.display()
+                )),
+                None => bundles.push(file),
+            }
+        }
+
+        bundles.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(bundles)
+    }
+}
+
+/// A template definition found in a template root, either a bundle (a
+/// directory holding `template.yml` and the template's files) or a flat
+/// `<name>.yml` file
+pub(crate) struct TemplateFile {
+    /// Name the template is selected by
+    pub id: String,
+    /// The YAML definition
+    pub path: PathBuf,
+    /// Directory `copy.from` is relative to: the bundle itself, or the root
+    /// for flat files
+    pub dir: PathBuf,
+}
+
+/// Directories templates are loaded from, highest precedence first. A
+/// template hides those of the same name in later roots:
+///
+/// 1. `.newnew/templates` in the current directory or the nearest parent
+///    that has one
+/// 2. Directories in `NEWNEW_TEMPLATE_PATH`, in order
+/// 3. `template_dirs` from `config_file`, or the config file in newnew's
+///    home if `None`, in order
+/// 4. The user's templates directory
+/// 5. The system templates directory
+///
+/// followed by template sources, whose templates are prefixed with the
+/// source name and so never clash with the others.
+pub fn template_roots(config_file: Option<&Path>, reporter: &mut dyn Reporter) -> io::Result<Vec<TemplateRoot>> {
+    let mut roots = Vec::new();
+
+    let local = env::current_dir()?
+        .ancestors()
+        .map(|dir| dir.join(".newnew").join("templates"))
+        .find(|dir| dir.is_dir());
+    if let Some(dir) = local {
+        roots.push(TemplateRoot::new("project", dir));
+    }
+
+    if let Some(paths) = env::var_os("NEWNEW_TEMPLATE_PATH") {
+        for dir in env::split_paths(&paths).filter(|dir| !dir.as_os_str().is_empty()) {
+            roots.push(TemplateRoot::new("NEWNEW_TEMPLATE_PATH", expand_path(&dir)?));
+        }
+    }
+
+    // An invalid config is reported where the rest of it is used
+    let template_dirs = Config::load(config_file)
+        .map(|config| config.settings.template_dirs)
+        .unwrap_or_default();
+    roots.extend(template_dirs
+        .into_iter()
+        .map(|dir| TemplateRoot::new("config", dir)));
+
+    roots.push(TemplateRoot::new("user", templates_dir()?));
+    roots.push(TemplateRoot::new("system", PathBuf::from(SYSTEM_TEMPLATE_DIR)));
+
+    for source in load_sources()? {
+        let root = source.root()?;
+        if !root.dir.exists() {
+            reporter.warning(&format!(
+                "Template source '{}' is missing. Run 'newnew template update {}' to clone it again.",
+                source.name, source.name
+            ));
+            continue;
+        }
+        roots.push(root);
+    }
+
+    Ok(roots)
+}
+
+/// Every template from the template roots of `config_file`, keyed by the name
+/// it is selected by, with `extends` applied
+pub fn load_templates(
+    config_file: Option<&Path>,
+    reporter: &mut dyn Reporter,
+) -> io::Result<HashMap<String, Template>> {
+    let mut templates = HashMap::new();
+    let mut had_errors = false;
+
+    for root in template_roots(config_file, reporter)? {
+        load_templates_from(&root, &mut templates, &mut had_errors, reporter)?;
     }
 
+    // Resolved against the templates as parsed, so a parent's own parent
+    // is applied exactly once
+    let parsed = templates.clone();
+    templates.retain(|name, template| match resolve_extends(template.clone(), &parsed) {
+        Ok(resolved) => {
+            *template = resolved;
+            true
+        },
+        Err(e) => {
+            had_errors = true;
+            reporter.warning(&format!("Error in template '{}': {}\n   This template will be skipped.", name, e));
+            false
+        }
+    });
+
     if templates.is_empty() {
         if had_errors {
             return Err(io::Error::new(
@@ -124,34 +503,111 @@ pub fn load_templates() -> io::Result<HashMap<String, Template>> {
     Ok(templates)
 }
 
-pub fn copy_example_templates_if_needed(with_examples: bool) -> io::Result<()> {
+/// Load the templates in `root` into `templates`, except those hidden by a
+/// template of the same name from an earlier root
+fn load_templates_from(
+    root: &TemplateRoot,
+    templates: &mut HashMap<String, Template>,
+    had_errors: &mut bool,
+    reporter: &mut dyn Reporter,
+) -> io::Result<()> {
+    for file in root.template_files(reporter)? {
+        if templates.contains_key(&file.id) {
+            continue;
+        }
+        let path = &file.path;
+
+        let loaded = fs::read_to_string(path)
+            .and_then(|content| template_hash(&file, &content).map(|hash| (content, hash)));
+        match loaded {
+            Ok((content, hash)) => {
+                match serde_yaml::from_str::<Template>(&content) {
+                    Ok(mut template) => {
+                        template.id = file.id.clone();
+                        template.hash = hash;
+                        template.dir = file.dir.clone();
+                        template.root = root.label.clone();
+                        templates.insert(file.id, template);
+                    },
+                    Err(e) => {
+                        *had_errors = true;
+                        let mut warning = format!(
+                            "Error parsing template '{}': {}\n   This template will be skipped. Please check the YAML format.",
+                            path.display(), e
+                        );
+
+                        // If it's specifically a variables format error, show migration help
+                        if e.to_string().contains("variables: invalid type: map") {
+                            warning.push_str(concat!(
+                                "\n\nℹ️  The template format has changed. Variables should now be a sequence.\n",
+                                "   Update your template from:\n",
+                                "   variables:\n",
+                                "     use_typescript:\n",
+                                "       prompt: \"Use TypeScript?\"\n",
+                                "\n   To:\n",
+                                "   variables:\n",
+                                "     - name: use_typescript\n",
+                                "       prompt: \"Use TypeScript?\"\n",
+                            ));
+                        }
+                        reporter.warning(&warning);
+                    }
+                }
+            },
+            Err(e) => {
+                *had_errors = true;
+                reporter.warning(&format!("Error reading template file '{}': {}", path.display(), e));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Hash of a template's definition and, for a bundle, every file in it, so
+/// changing a file a bundle copies counts as changing the template
+fn template_hash(file: &TemplateFile, content: &str) -> io::Result<String> {
+    if !file.path.ends_with(BUNDLE_FILE) {
+        return Ok(hash_content(content));
+    }
+
+    let mut hashed = content.to_string();
+    for relative in list_files_recursive(&file.dir)? {
+        if relative == Path::new(BUNDLE_FILE) {
+            continue;
+        }
+        let file_hash = hash_content(fs::read(file.dir.join(&relative))?);
+        hashed.push_str(&format!("\0{}\0{}", relative.to_string_lossy().replace('\\', "/"), file_hash));
+    }
+    Ok(hash_content(hashed))
+}
+
+pub(crate) fn hash_content(content: impl AsRef<[u8]>) -> String {
+    Sha256::digest(content.as_ref())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+pub fn copy_example_templates_if_needed(with_examples: bool, reporter: &mut dyn Reporter) -> io::Result<()> {
     if !with_examples {
         return Ok(());
     }
 
-    let template_dir = get_template_dir()?;
+    let template_dir = templates_dir()?;
     if !template_dir.exists() {
         fs::create_dir_all(&template_dir)?;
     }
     
-    copy_example_templates(&template_dir)
-}
-
-fn get_template_dir() -> io::Result<PathBuf> {
-    dirs::home_dir()
-        .map(|p| p.join(".config").join("newnew").join("templates"))
-        .ok_or_else(|| io::Error::new(
-            io::ErrorKind::NotFound,
-            "Could not determine config directory"
-        ))
+    copy_example_templates(&template_dir, reporter)
 }
 
-fn copy_example_templates(template_dir: &Path) -> io::Result<()> {
-    println!("Copying templates to: {}", template_dir.display()); // Temporary debug
+fn copy_example_templates(template_dir: &Path, reporter: &mut dyn Reporter) -> io::Result<()> {
+    reporter.progress(&format!("Copying templates to: {}", template_dir.display()));
     let example_dir = Path::new("examples/templates");
     
     if !example_dir.exists() {
-        println!("Example dir not found: {}", example_dir.display()); // Temporary debug
+        reporter.warning(&format!("Example dir not found: {}", example_dir.display()));
         return Ok(());
     }
 
@@ -162,7 +618,7 @@ fn copy_example_templates(template_dir: &Path) -> io::Result<()> {
         
         if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("yml") {
             let dest = template_dir.join(path.file_name().unwrap());
-            println!("Copying YAML: {} -> {}", path.display(), dest.display()); // Temporary debug
+            reporter.progress(&format!("Copying YAML: {} -> {}", path.display(), dest.display()));
             fs::copy(&path, dest)?;
         }
     }
@@ -175,7 +631,7 @@ fn copy_example_templates(template_dir: &Path) -> io::Result<()> {
         if path.is_dir() {
             let dir_name = path.file_name().unwrap();
             let dest_dir = template_dir.join(dir_name);
-            println!("Copying dir: {} -> {}", path.display(), dest_dir.display()); // Temporary debug
+            reporter.progress(&format!("Copying dir: {} -> {}", path.display(), dest_dir.display()));
             fs::create_dir_all(&dest_dir)?;
             copy_dir_recursive(&path, &dest_dir)?;
         }
@@ -189,17 +645,35 @@ fn copy_dir_recursive(src: &Path, dst: &Path) -> io::Result<()> {
         fs::create_dir_all(dst)?;
     }
 
-    for entry in fs::read_dir(src)? {
+    for relative in list_files_recursive(src)? {
+        let dest = dst.join(&relative);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(src.join(&relative), &dest)?;
+    }
+    Ok(())
+}
+
+/// All files below `dir`, as paths relative to it, in a stable order
+pub fn list_files_recursive(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    collect_files(dir, Path::new(""), &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+fn collect_files(dir: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
         let entry = entry?;
         let ty = entry.file_type()?;
-        let source = entry.path();
-        let dest = dst.join(source.file_name().unwrap());
+        let relative = relative.join(entry.file_name());
 
         if ty.is_dir() {
-            copy_dir_recursive(&source, &dest)?;
+            collect_files(&entry.path(), &relative, files)?;
         } else {
-            fs::copy(&source, &dest)?;
+            files.push(relative);
         }
     }
     Ok(())
-} 
\ No newline at end of file
+}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::answers::{
+    RecordedAnswers, contains_password, load_recorded, read_merge_base, write_answers, write_merge_base,
+};
+use crate::config::Config;
+use crate::copy::{RenderedFile, render_copy};
+use crate::project::{ProjectOptions, prompt_project_config, step_skip_reason};
+use crate::prompt::Prompter;
+use crate::report::Reporter;
+use crate::template::hash_content;
+use crate::value::Value;
+
+pub struct UpdateOptions {
+    pub project_path: PathBuf,
+    /// Values for variables the template gained since the project was created
+    pub variables: HashMap<String, Value>,
+    pub assume_defaults: bool,
+    /// Config file to use instead of `newnew.toml` in newnew's home
+    pub config_file: Option<PathBuf>,
+}
+
+/// What happened to a single file while updating
+enum FileUpdate {
+    Added,
+    Updated,
+    Merged,
+    Conflict,
+    Removed,
+    Unchanged,
+    Skipped(&'static str),
+}
+
+/// Re-render the copy steps of the template a project was generated from and
+/// three-way merge the result into the project, using the files kept at
+/// generation time as the common ancestor. `run` steps are not re-executed.
+/// Variables the template has gained are asked for with `prompter`, and what
+/// happens to each file goes to `reporter`. Fails if conflicts are left to
+/// resolve.
+pub fn update_project(
+    options: UpdateOptions,
+    prompter: &mut dyn Prompter,
+    reporter: &mut dyn Reporter,
+) -> io::Result<()> {
+    let settings = Config::load(options.config_file.as_deref()).unwrap_or_default().settings;
+    let project_path = options.project_path.canonicalize()
+        .map_err(|e| io::Error::new(
+            e.kind(),
+            format!("Project directory '{}' not found: {}", options.project_path.display(), e)
+        ))?;
+
+    let answers_path = project_path.join(&settings.answers_file);
+    if !answers_path.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "No recorded answers found at '{}'. Only projects generated by newnew can be updated.",
+                answers_path.display()
+            )
+        ));
+    }
+    let recorded = load_recorded(&answers_path)?;
+
+    reporter.progress(&format!("🔄 Updating '{}' from template '{}'", project_path.display(), recorded.template));
+
+    // Resolve variables from the recorded answers, only asking for ones the
+    // template has gained since
+    let mut variables: HashMap<String, Value> = recorded.variables.clone().into_iter().collect();
+    variables.extend(options.variables);
+    let mut config = prompt_project_config(ProjectOptions {
+        target_dir: project_path.parent().map(|p| p.to_string_lossy().into_owned()),
+        template: Some(recorded.template.clone()),
+        variables,
+        assume_defaults: options.assume_defaults,
+        config_file: options.config_file,
+        ..ProjectOptions::default()
+    }, prompter, reporter)?;
+    // Keep the date, uuid etc. from when the project was generated so they
+    // don't show up as changes
+    config.builtins.extend(recorded.builtins.clone());
+
+    let context = config.context(&project_path);
+
+    // Render the current version of the template in memory
+    let mut rendered = Vec::new();
+    for step in &config.template.steps {
+        let Some(copy) = &step.copy else { continue };
+        if step_skip_reason(step, &context).is_some() {
+            continue;
+        }
+        rendered.extend(render_copy(copy, &config.template.dir, &context)?);
+    }
+
+    let mut conflicts = Vec::new();
+    let mut rendered_paths = Vec::new();
+    for file in &rendered {
+        let key = file.key();
+        let result = if contains_password(&config, file) && project_path.join(&file.path).exists() {
+            FileUpdate::Skipped("contains a password")
+        } else {
+            merge_file(&project_path, file, recorded.files.get(&key))?
+        };
+        if let FileUpdate::Conflict = result {
+            conflicts.push(key.clone());
+        }
+        report(&key, &result, reporter);
+        rendered_paths.push(key);
+    }
+
+    // Files the template no longer produces
+    for (key, hash) in &recorded.files {
+        if !rendered_paths.contains(key) {
+            let result = remove_file(&project_path, key, hash)?;
+            report(key, &result, reporter);
+        }
+    }
+
+    write_answers(&answers_path, &RecordedAnswers::new(&config, &rendered))?;
+    write_merge_base(&project_path, &config, &rendered)?;
+
+    if !conflicts.is_empty() {
+        return Err(io::Error::other(format!(
+            "{} file(s) have conflicts. Resolve the conflict markers in: {}",
+            conflicts.len(),
+            conflicts.join(", ")
+        )));
+    }
+
+    Ok(())
+}
+
+/// Bring one file up to date. `hash` is the hash of the file as recorded
+/// when the project was last generated or updated, if it was recorded.
+fn merge_file(project_path: &Path, file: &RenderedFile, hash: Option<&String>) -> io::Result<FileUpdate> {
+    let dest = project_path.join(&file.path);
+    let theirs = &file.content;
+
+    if !dest.exists() {
+        return match hash {
+            None => {
+                file.write_to(project_path)?;
+                Ok(FileUpdate::Added)
+            },
+            Some(_) => Ok(FileUpdate::Skipped("deleted in project")),
+        };
+    }
+
+    let ours = fs::read(&dest)?;
+    if &ours == theirs {
+        return Ok(FileUpdate::Unchanged);
+    }
+    match hash {
+        // Unchanged in the template, keep the project's version
+        Some(hash) if hash_content(theirs) == *hash => return Ok(FileUpdate::Unchanged),
+        // Unchanged in the project, take the template's version
+        Some(hash) if hash_content(&ours) == *hash => {
+            file.write_to(project_path)?;
+            return Ok(FileUpdate::Updated);
+        },
+        _ => {}
+    }
+
+    let (Ok(ours), Some(theirs)) = (String::from_utf8(ours), file.text()) else {
+        return Ok(FileUpdate::Skipped("binary file modified in project"));
+    };
+    // Without a merge base every difference is a conflict
+    let base = hash
+        .and_then(|hash| read_merge_base(project_path, &file.key(), hash))
+        .and_then(|base| String::from_utf8(base).ok())
+        .unwrap_or_default();
+    let (content, result) = match diffy::merge(&base, &ours, theirs) {
+        Ok(merged) => (merged, FileUpdate::Merged),
+        Err(conflicted) => (conflicted, FileUpdate::Conflict),
+    };
+    fs::write(&dest, content)?;
+    Ok(result)
+}
+
+fn remove_file(project_path: &Path, key: &str, hash: &str) -> io::Result<FileUpdate> {
+    let dest = project_path.join(key);
+    if !dest.exists() {
+        return Ok(FileUpdate::Unchanged);
+    }
+
+    if hash_content(fs::read(&dest)?) == hash {
+        fs::remove_file(&dest)?;
+        Ok(FileUpdate::Removed)
+    } else {
+        Ok(FileUpdate::Skipped("removed from template but modified in project"))
+    }
+}
+
+fn report(path: &str, result: &FileUpdate, reporter: &mut dyn Reporter) {
+    let message = match result {
+        FileUpdate::Added => format!("  ➕ {} (added)", path),
+        FileUpdate::Updated => format!("  ✔ {} (updated)", path),
+        FileUpdate::Merged => format!("  🔀 {} (merged)", path),
+        FileUpdate::Conflict => format!("  ⚠️  {} (conflict)", path),
+        FileUpdate::Removed => format!("  ➖ {} (removed)", path),
+        FileUpdate::Unchanged => return,
+        FileUpdate::Skipped(reason) => format!("  ↪ {} (skipped: {})", path, reason),
+    };
+    reporter.progress(&message);
+}
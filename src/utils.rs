@@ -1,48 +1,99 @@
 use std::io::{self, Write};
 use std::process::Command;
-use dialoguer::{theme::ColorfulTheme, Select, Confirm, MultiSelect};
+use dialoguer::{theme::ColorfulTheme, Select, Confirm, MultiSelect, Password};
+use crate::report::Reporter;
 
-pub fn prompt_input(prompt: &str) -> String {
+pub fn prompt_input(prompt: &str) -> io::Result<String> {
     print!("{prompt}: ");
-    io::stdout().flush().unwrap();
-    
+    io::stdout().flush()?;
+
     let mut input = String::new();
-    io::stdin()
-        .read_line(&mut input)
-        .expect("Failed to read input");
-    
-    input.trim().to_string()
+    if io::stdin().read_line(&mut input)? == 0 {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!("No input for '{}'", prompt)));
+    }
+
+    Ok(input.trim().to_string())
 }
 
-pub fn prompt_select(prompt: &str, options: &[String]) -> String {
-    let selection = Select::with_theme(&ColorfulTheme::default())
+/// Index of the chosen option
+pub fn prompt_select(prompt: &str, options: &[String]) -> io::Result<usize> {
+    Select::with_theme(&ColorfulTheme::default())
         .with_prompt(prompt)
         .items(options)
         .default(0)
         .interact()
-        .unwrap();
-
-    options[selection].clone()
+        .map_err(dialoguer_error)
 }
 
-pub fn prompt_confirm(prompt: &str, default: bool) -> bool {
+pub fn prompt_confirm(prompt: &str, default: bool) -> io::Result<bool> {
     Confirm::with_theme(&ColorfulTheme::default())
         .with_prompt(prompt)
         .default(default)
         .interact()
-        .unwrap()
+        .map_err(dialoguer_error)
 }
 
-pub fn prompt_multiselect(prompt: &str, options: &[String]) -> Vec<String> {
+pub fn prompt_multiselect(prompt: &str, options: &[String], defaults: &[String]) -> io::Result<Vec<String>> {
+    let checked: Vec<bool> = options.iter().map(|option| defaults.contains(option)).collect();
     let selections = MultiSelect::with_theme(&ColorfulTheme::default())
         .with_prompt(prompt)
         .items(options)
+        .defaults(&checked)
         .interact()
-        .unwrap();
+        .map_err(dialoguer_error)?;
 
-    selections.into_iter()
+    Ok(selections.into_iter()
         .map(|i| options[i].clone())
-        .collect()
+        .collect())
+}
+
+pub fn prompt_password(prompt: &str) -> io::Result<String> {
+    Password::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .allow_empty_password(true)
+        .interact()
+        .map_err(dialoguer_error)
+}
+
+fn dialoguer_error(e: dialoguer::Error) -> io::Error {
+    let dialoguer::Error::IO(e) = e;
+    e
+}
+
+/// Keep prompting until `validate` accepts the answer, showing why it was
+/// rejected each time. Fails if prompting itself fails, e.g. when input ends.
+pub fn prompt_until_valid<T, U>(
+    mut prompt: impl FnMut() -> io::Result<T>,
+    validate: impl Fn(T) -> Result<U, String>,
+    reporter: &mut dyn Reporter,
+) -> io::Result<U> {
+    loop {
+        match validate(prompt()?) {
+            Ok(value) => return Ok(value),
+            Err(e) => reporter.warning(&e),
+        }
+    }
+}
+
+/// Run a shell command and return its trimmed standard output
+pub fn capture_command(command: &str) -> io::Result<String> {
+    command_output(Command::new("sh").arg("-c").arg(command), &format!("command '{}'", command))
+}
+
+/// Run `command` and return its trimmed standard output. If it fails, the
+/// error says `<what> failed:` followed by its standard error, or its exit
+/// status if it printed nothing.
+pub(crate) fn command_output(command: &mut Command, what: &str) -> io::Result<String> {
+    let output = command.output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = match stderr.trim() {
+            "" => output.status.to_string(),
+            stderr => stderr.to_string(),
+        };
+        return Err(io::Error::other(format!("{} failed: {}", what, reason)));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
 
 pub fn check_command_exists(command: &str) -> bool {
@@ -51,4 +102,4 @@ pub fn check_command_exists(command: &str) -> bool {
         .output()
         .map(|output| output.status.success())
         .unwrap_or(false)
-} 
\ No newline at end of file
+}
@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The value of a template variable
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Value {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    List(Vec<String>),
+}
+
+impl Value {
+    /// Whether the value counts as "set" in `if`/`if-not` conditions: true
+    /// booleans, non-zero numbers and non-empty lists and strings (other
+    /// than "false").
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Bool(b) => *b,
+            Value::Integer(i) => *i != 0,
+            Value::Float(f) => *f != 0.0,
+            Value::String(s) => !s.is_empty() && s != "false",
+            Value::List(items) => !items.is_empty(),
+        }
+    }
+}
+
+/// Formats values the way they are substituted into commands and files;
+/// lists are joined with commas.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Integer(i) => write!(f, "{}", i),
+            Value::Float(x) => write!(f, "{}", x),
+            Value::String(s) => write!(f, "{}", s),
+            Value::List(items) => write!(f, "{}", items.join(",")),
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
@@ -0,0 +1,463 @@
+mod common;
+
+use common::{OVERRIDE_VARS, TestHome, read};
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn newnew(home: &TestHome, args: &[&str]) -> Output {
+    command(home).args(args).output().unwrap()
+}
+
+fn command(home: &TestHome) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_newnew"));
+    command.env("HOME", home.path());
+    for name in OVERRIDE_VARS {
+        command.env_remove(name);
+    }
+    command
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn generates_non_interactively() {
+    let home = TestHome::with_service_template();
+    let projects = home.projects().to_string_lossy().into_owned();
+
+    let output = newnew(&home, &[
+        "-t", "service", "-n", "cli-app", "-y", "-d", &projects,
+        "--var", "description=From the command line",
+        "--var", "kind=Library",
+        "--var", "features=web,db",
+        "--var", "port=3000",
+    ]);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let project = home.projects().join("cli-app");
+    assert!(read(project.join("README.md")).contains("From the command line"));
+    assert!(project.join("src/cli_app.rs").exists());
+    assert!(project.join("lib.marker").exists());
+    assert!(read(project.join(".newnew-answers.yml")).contains("port: 3000"));
+    // The config written on first use shows every setting
+    let config = read(home.path().join(".config/newnew/newnew.toml"));
+    for setting in ["projects_dir", "answers_file", "project_name_pattern", "template_dirs"] {
+        assert!(config.contains(setting), "{} missing from:\n{}", setting, config);
+    }
+}
+
+#[test]
+fn missing_values_fail_without_prompting() {
+    let home = TestHome::with_service_template();
+    let projects = home.projects().to_string_lossy().into_owned();
+
+    let output = newnew(&home, &["-t", "service", "-n", "incomplete", "-y", "-d", &projects]);
+
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Missing values"), "{}", stderr(&output));
+    assert!(!home.projects().join("incomplete").exists());
+}
+
+#[test]
+fn answers_files_fill_in_variables() {
+    let home = TestHome::with_service_template();
+    let projects = home.projects().to_string_lossy().into_owned();
+    let files = [
+        ("answers.yml", "project_name: from-yaml\ndescription: From YAML\nkind: Library\nfeatures: [web]\nport: 3000\n"),
+        ("answers.toml", "project_name = \"from-toml\"\ndescription = \"From TOML\"\nkind = \"Library\"\nfeatures = [\"web\"]\nport = 3000\n"),
+        ("answers.json", r#"{"project_name": "from-json", "description": "From JSON", "kind": "Library", "features": ["web"], "port": 3000}"#),
+    ];
+
+    for (file, content) in files {
+        let path = home.path().join(file);
+        fs::write(&path, content).unwrap();
+        let output = newnew(&home, &["-t", "service", "-y", "-d", &projects, "--answers", path.to_str().unwrap()]);
+        assert!(output.status.success(), "{}: {}", file, stderr(&output));
+    }
+
+    for (name, description) in [("from-yaml", "From YAML"), ("from-toml", "From TOML"), ("from-json", "From JSON")] {
+        let project = home.projects().join(name);
+        assert!(read(project.join("README.md")).contains(description));
+        assert!(project.join("lib.marker").exists());
+        assert!(read(project.join(".newnew-answers.yml")).contains("port: 3000"));
+    }
+
+    // A recorded answers file replays the generation, template included
+    let recorded = home.projects().join("from-yaml/.newnew-answers.yml");
+    let output = newnew(&home, &["-n", "replayed", "-y", "-d", &projects, "--answers", recorded.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(read(home.projects().join("replayed/README.md")).contains("From YAML"));
+}
+
+#[test]
+fn answers_files_are_validated() {
+    let home = TestHome::with_service_template();
+    let projects = home.projects().to_string_lossy().into_owned();
+    let cases = [
+        ("kind.yml", "description: Valid\nkind: Plugin\n", "expected one of Binary, Library"),
+        ("features.toml", "description = \"Valid\"\nfeatures = [\"web\", \"gui\"]\n", "'gui'"),
+        ("port.json", r#"{"description": "Valid", "features": ["web"], "port": "many"}"#, "expected a whole number"),
+        ("docker.yml", "description: Valid\nuse_docker: maybe\n", "expected true or false"),
+        // A recorded file with values of the wrong shape is an error, not a
+        // flat file with a variable called `variables`
+        ("recorded.yml", "template: service\nvariables:\n  description: [not, a, string]\n", "Invalid value"),
+        ("broken.yml", "template: service\nvariables: {description: Valid\n", "Failed to parse"),
+    ];
+
+    for (file, content, error) in cases {
+        let path = home.path().join(file);
+        fs::write(&path, content).unwrap();
+        let output = newnew(&home, &["-t", "service", "-n", "invalid", "-y", "-d", &projects, "--answers", path.to_str().unwrap()]);
+        assert!(!output.status.success(), "{} was accepted", file);
+        assert!(stderr(&output).contains(error), "{}: {}", file, stderr(&output));
+    }
+    assert!(!home.projects().join("invalid").exists());
+}
+
+#[test]
+fn dry_run_creates_nothing() {
+    let home = TestHome::with_service_template();
+    let projects = home.projects().to_string_lossy().into_owned();
+
+    let output = newnew(&home, &[
+        "-t", "service", "-n", "planned", "-y", "-d", &projects, "--dry-run",
+        "--var", "description=Only a plan",
+        "--var", "use_docker=true",
+    ]);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("EXPOSE"));
+    assert!(!home.projects().join("planned").exists());
+
+    // Planning in a fresh home doesn't set it up either
+    let fresh = home.path().join("fresh");
+    let output = command(&home)
+        .args(["-t", "service", "-n", "planned", "-y", "-d", &projects, "--dry-run", "--var", "description=Plan"])
+        .env("NEWNEW_HOME", &fresh)
+        .env("NEWNEW_TEMPLATE_PATH", home.templates())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(!fresh.exists());
+}
+
+#[test]
+fn dry_run_shows_commands_instead_of_running_them() {
+    let home = TestHome::new();
+    let marker = home.path().join("ran");
+    home.template("probe", &format!(
+        "name: Probe\ndescription: x\nemoji: \"x\"\nvariables:\n  - name: author\n    command: touch '{0}' && echo Ada\n  - name: email\n    prompt: Email\n    command: touch '{0}' && echo ada@example.com\nsteps:\n  - name: Greet\n    run: echo {{author}} {{email}}\n",
+        marker.display()
+    ));
+    let projects = home.projects().to_string_lossy().into_owned();
+
+    let output = newnew(&home, &["-t", "probe", "-n", "planned", "-y", "-d", &projects, "--dry-run"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(!marker.exists());
+    let plan = String::from_utf8_lossy(&output.stdout);
+    assert!(plan.contains(&format!("author = <output of 'touch '{}' && echo Ada'>", marker.display())), "{}", plan);
+    // Built-ins read from git are placeholders too
+    assert!(plan.contains("git_user_name = <output of 'git config user.name'>"), "{}", plan);
+    assert!(plan.contains("git_user_email = <output of 'git config user.email'>"), "{}", plan);
+
+    let output = newnew(&home, &["-t", "probe", "-n", "real", "-y", "-d", &projects]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(marker.exists());
+}
+
+#[test]
+fn lint_reports_unknown_keys() {
+    let home = TestHome::new();
+    home.template("unknown", "name: Unknown\ndescription: Has an unknown key\nemoji: \"🐛\"\nsteps: []\nauthor: nobody\n");
+
+    let output = newnew(&home, &["lint", "unknown"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let report = format!("{}{}", String::from_utf8_lossy(&output.stdout), stderr(&output));
+    assert!(report.contains("author"), "{}", report);
+}
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+        .status;
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+#[test]
+fn installs_and_updates_templates_from_git() {
+    let home = TestHome::new();
+    let repo = home.path().join("repo");
+    fs::create_dir_all(repo.join("greeting")).unwrap();
+    fs::write(repo.join("greeting.yml"), "name: Greeting\ndescription: From git\nemoji: \"👋\"\nsteps:\n  - name: Copy\n    copy:\n      from: greeting\n      to: .\n").unwrap();
+    fs::write(repo.join("greeting/hello.txt"), "v1 {{ project_name }}\n").unwrap();
+    git(&repo, &["init", "--quiet"]);
+    git(&repo, &["add", "-A"]);
+    git(&repo, &["commit", "--quiet", "-m", "v1"]);
+    git(&repo, &["tag", "v1"]);
+    fs::write(repo.join("greeting/hello.txt"), "v2 {{ project_name }}\n").unwrap();
+    git(&repo, &["commit", "--quiet", "-am", "v2"]);
+
+    let url = format!("file://{}", repo.display());
+    let output = newnew(&home, &["template", "add", &url, "--name", "acme", "--ref", "v1"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("acme/greeting"));
+
+    let projects = home.projects().to_string_lossy().into_owned();
+    let output = newnew(&home, &["-t", "acme/greeting", "-n", "pinned", "-y", "-d", &projects]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(read(home.projects().join("pinned/hello.txt")), "v1 pinned\n");
+
+    // Dropping the ref follows the default branch from the next update
+    let registry = home.path().join(".config/newnew/sources.toml");
+    fs::write(&registry, read(&registry).replace("ref = \"v1\"\n", "")).unwrap();
+    let output = newnew(&home, &["template", "update", "acme"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let output = newnew(&home, &["-t", "acme/greeting", "-n", "latest", "-y", "-d", &projects]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(read(home.projects().join("latest/hello.txt")), "v2 latest\n");
+
+    let output = newnew(&home, &["template", "add", &url, "--name", "acme"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("already exists"));
+}
+
+#[test]
+fn source_urls_are_not_git_options() {
+    let home = TestHome::new();
+
+    let output = newnew(&home, &["template", "add", "--name", "bare", "--", "--bare"]);
+    assert!(!output.status.success());
+    // Cloned as a repository called '--bare', which doesn't exist
+    assert!(stderr(&output).contains("'--bare'"), "{}", stderr(&output));
+    assert!(!home.path().join(".config/newnew/sources/bare").exists());
+
+    // Nor are refs, whether given when adding or found in the registry
+    let repo = home.path().join("repo").to_string_lossy().into_owned();
+    let output = newnew(&home, &["template", "add", &repo, "--name", "acme", "--ref=--orphan=main"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Invalid ref '--orphan=main'"), "{}", stderr(&output));
+    assert!(!home.path().join(".config/newnew/sources/acme").exists());
+
+    let registry = home.path().join(".config/newnew/sources.toml");
+    fs::write(&registry, format!("[[sources]]\nname = \"acme\"\nurl = {:?}\nref = \"--orphan=main\"\n", repo)).unwrap();
+    let output = newnew(&home, &["template", "update", "acme"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Invalid ref '--orphan=main'"), "{}", stderr(&output));
+}
+
+#[test]
+fn template_roots_override_in_order() {
+    let home = TestHome::new();
+    let template = |description: &str| format!(
+        "name: Greeting\ndescription: {0}\nemoji: \"👋\"\nsteps:\n  - name: Say\n    run: echo from-{0}\n",
+        description
+    );
+    home.template("greeting", &template("user"));
+    home.template("user-only", &template("user"));
+
+    let project = home.path().join("work");
+    let nested = project.join("src/deep");
+    fs::create_dir_all(&nested).unwrap();
+    fs::create_dir_all(project.join(".newnew/templates")).unwrap();
+    fs::write(project.join(".newnew/templates/greeting.yml"), template("project")).unwrap();
+
+    let env_dir = home.path().join("env-templates");
+    fs::create_dir_all(&env_dir).unwrap();
+    fs::write(env_dir.join("greeting.yml"), template("env")).unwrap();
+    fs::write(env_dir.join("user-only.yml"), template("env")).unwrap();
+
+    let plan = |template: &str, dir: &Path| {
+        let output = command(&home)
+            .args(["-t", template, "-n", "planned", "-y", "--dry-run"])
+            .env("NEWNEW_TEMPLATE_PATH", &env_dir)
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", stderr(&output));
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    // The project's own templates are found from any subdirectory
+    assert!(plan("greeting", &nested).contains("from-project"));
+    // Outside the project, NEWNEW_TEMPLATE_PATH comes before the user's
+    assert!(plan("greeting", home.path()).contains("from-env"));
+    assert!(plan("user-only", &nested).contains("from-env"));
+}
+
+#[test]
+fn config_location_follows_environment_and_flag() {
+    let home = TestHome::new();
+    let greeting = |from: &str| format!(
+        "name: Greeting\ndescription: Hi\nemoji: \"👋\"\nsteps:\n  - name: Say\n    run: echo from-{}\n",
+        from
+    );
+    let plan = |command: &mut Command| {
+        let output = command.args(["-t", "greeting", "-n", "planned", "-y", "--dry-run"]).output().unwrap();
+        assert!(output.status.success(), "{}", stderr(&output));
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    let xdg = home.path().join("xdg");
+    fs::create_dir_all(xdg.join("newnew/templates")).unwrap();
+    fs::write(xdg.join("newnew/templates/greeting.yml"), greeting("xdg")).unwrap();
+    assert!(plan(command(&home).env("XDG_CONFIG_HOME", &xdg)).contains("from-xdg"));
+    assert!(!xdg.join("newnew/newnew.toml").exists());
+
+    let newnew_home = home.path().join("newnew-home");
+    fs::create_dir_all(newnew_home.join("templates")).unwrap();
+    fs::write(newnew_home.join("templates/greeting.yml"), greeting("newnew-home")).unwrap();
+    let output = plan(command(&home).env("XDG_CONFIG_HOME", &xdg).env("NEWNEW_HOME", &newnew_home));
+    assert!(output.contains("from-newnew-home"));
+
+    // Paths in the config expand variables and resolve relative to the file
+    let team = home.path().join("team");
+    fs::create_dir_all(team.join("templates")).unwrap();
+    fs::write(team.join("templates/greeting.yml"), greeting("team")).unwrap();
+    fs::write(team.join("newnew.toml"), "[settings]\nprojects_dir = \"$WORK/dev\"\ntemplate_dirs = [\"templates\"]\n").unwrap();
+    let config = team.join("newnew.toml");
+    let output = plan(command(&home).env("WORK", "/srv/work").args(["--config", config.to_str().unwrap()]));
+    assert!(output.contains("from-team"));
+    assert!(output.contains("/srv/work/dev/planned"));
+
+    let output = newnew(&home, &["--config", "missing.toml", "lint"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("missing.toml"));
+}
+
+#[test]
+fn lint_checks_templates_with_their_parents() {
+    let home = TestHome::new();
+    home.template("base", "name: Base\ndescription: x\nemoji: \"x\"\nvariables:\n  - name: docker\n    prompt: Docker?\n    type: boolean\nsteps:\n  - name: Build\n    run: make\n");
+    home.template("child", "name: Child\ndescription: x\nemoji: \"x\"\nextends: base\nsteps:\n  - name: Dockerfile\n    if: docker\n    after: Build\n    run: touch Dockerfile\n");
+    home.template("broken", "name: Broken\ndescription: x\nemoji: \"x\"\nextends: base\nsteps:\n  - name: Deploy\n    before: Release\n    run: deploy\n");
+
+    let output = newnew(&home, &["lint", "child"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+
+    let output = newnew(&home, &["lint", "broken"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("'Release'"));
+}
+
+#[test]
+fn update_merges_template_changes() {
+    let home = TestHome::new();
+    home.template("app", "name: App\ndescription: x\nemoji: \"x\"\nvariables:\n  - name: password\n    prompt: Password\n    type: password\nsteps:\n  - name: Copy\n    copy:\n      from: app\n      to: .\n");
+    home.template_file("app/updated.txt", "one\ntwo\n");
+    home.template_file("app/merged.txt", "a\nb\nc\nd\ne\n");
+    home.template_file("app/conflict.txt", "x\n");
+    home.template_file("app/removed.txt", "old\n");
+    home.template_file("app/deleted.txt", "v1\n");
+    home.template_file("app/.env", "PASSWORD={{ password }}\n");
+
+    let projects = home.projects().to_string_lossy().into_owned();
+    let output = newnew(&home, &["-t", "app", "-n", "app", "-y", "-d", &projects, "--var", "password=hunter2"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    // The answers file holds hashes, not contents, and nothing about the
+    // file with the password
+    let project = home.projects().join("app");
+    let answers = read(project.join(".newnew-answers.yml"));
+    assert!(answers.contains("updated.txt"));
+    assert!(!answers.contains("one") && !answers.contains("hunter2") && !answers.contains(".env"), "{}", answers);
+    assert_eq!(read(project.join(".newnew/base/.gitignore")), "*\n");
+
+    fs::write(project.join("merged.txt"), "A\nb\nc\nd\ne\n").unwrap();
+    fs::write(project.join("conflict.txt"), "ours\n").unwrap();
+    fs::remove_file(project.join("deleted.txt")).unwrap();
+    fs::write(project.join(".env"), "PASSWORD=changed\n").unwrap();
+
+    home.template_file("app/updated.txt", "one\ntwo\nthree\n");
+    home.template_file("app/merged.txt", "a\nb\nc\nd\nE\n");
+    home.template_file("app/conflict.txt", "theirs\n");
+    fs::remove_file(home.templates().join("app/removed.txt")).unwrap();
+    home.template_file("app/deleted.txt", "v2\n");
+    home.template_file("app/added.txt", "new\n");
+
+    let project_arg = project.to_string_lossy().into_owned();
+    let output = newnew(&home, &["update", "--path", &project_arg, "--var", "password=hunter2"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success(), "conflicts should fail the update: {}", stdout);
+    assert!(stderr(&output).contains("conflict.txt"), "{}", stderr(&output));
+
+    assert_eq!(read(project.join("updated.txt")), "one\ntwo\nthree\n");
+    assert_eq!(read(project.join("merged.txt")), "A\nb\nc\nd\nE\n");
+    assert!(read(project.join("conflict.txt")).contains("<<<<<<<"));
+    assert!(!project.join("removed.txt").exists());
+    assert!(!project.join("deleted.txt").exists());
+    assert_eq!(read(project.join("added.txt")), "new\n");
+    assert_eq!(read(project.join(".env")), "PASSWORD=changed\n");
+    for expected in [
+        "updated.txt (updated)",
+        "merged.txt (merged)",
+        "conflict.txt (conflict)",
+        "removed.txt (removed)",
+        "deleted.txt (skipped: deleted in project)",
+        "added.txt (added)",
+        ".env (skipped: contains a password)",
+    ] {
+        assert!(stdout.contains(expected), "missing '{}' in:\n{}", expected, stdout);
+    }
+
+    // Once the conflict is resolved there is nothing left to do
+    fs::write(project.join("conflict.txt"), "resolved\n").unwrap();
+    let output = newnew(&home, &["update", "--path", &project_arg, "--var", "password=hunter2"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(read(project.join("conflict.txt")), "resolved\n");
+}
+
+#[test]
+fn update_touches_nothing_outside_the_project() {
+    let home = TestHome::new();
+    home.template("app", "name: App\ndescription: x\nemoji: \"x\"\nsteps:\n  - name: Copy\n    copy:\n      from: app\n      to: .\n");
+    home.template_file("app/victim.txt", "precious\n");
+
+    let projects = home.projects().to_string_lossy().into_owned();
+    let output = newnew(&home, &["-t", "app", "-n", "app", "-y", "-d", &projects]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    // A tampered answers file pointing at a file with the same contents
+    // outside the project
+    let project = home.projects().join("app");
+    let victim = home.projects().join("victim.txt");
+    fs::write(&victim, "precious\n").unwrap();
+    let answers = project.join(".newnew-answers.yml");
+    fs::write(&answers, read(&answers).replace("victim.txt:", "../victim.txt:")).unwrap();
+
+    let project_arg = project.to_string_lossy().into_owned();
+    let output = newnew(&home, &["update", "--path", &project_arg]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("'../victim.txt' is not inside the project"), "{}", stderr(&output));
+    assert_eq!(read(&victim), "precious\n");
+}
+
+#[test]
+fn lint_skips_files_that_are_not_rendered() {
+    let home = TestHome::new();
+    let template = |filters: &str| format!(
+        "name: CI\ndescription: x\nemoji: \"x\"\nsteps:\n  - name: Copy\n    copy:\n      from: ci\n      to: .\n{}",
+        filters
+    );
+    home.template("filtered", &template("      no_render: [\"*.yml\"]\n      exclude: [\"skip/**\"]\n"));
+    home.template("unfiltered", &template(""));
+    home.template_file("ci/.github/workflows/ci.yml", "token: ${{ secrets.TOKEN }}\n");
+    home.template_file("ci/skip/broken.txt", "{% if %}\n");
+    home.template_file("ci/README.md", "# {{ project_name }}\n");
+
+    let output = newnew(&home, &["lint", "filtered"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+
+    let output = newnew(&home, &["lint", "unfiltered"]);
+    let report = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(report.contains("undefined variable 'secrets'"), "{}", report);
+    assert!(report.contains("skip/broken.txt"), "{}", report);
+}
@@ -0,0 +1,117 @@
+#![allow(dead_code)]
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use tempfile::TempDir;
+
+/// Templates are found through `$HOME`, which is process-wide, so tests
+/// that run the library in-process take turns
+static HOME_LOCK: Mutex<()> = Mutex::new(());
+
+/// Variables that move newnew's directories away from `$HOME`, cleared so
+/// the environment the tests run in doesn't leak into them
+pub const OVERRIDE_VARS: [&str; 3] = ["NEWNEW_HOME", "XDG_CONFIG_HOME", "NEWNEW_TEMPLATE_PATH"];
+
+pub const SERVICE_TEMPLATE: &str = r#"
+name: Service
+description: A test service
+emoji: "🧪"
+variables:
+  - name: description
+    prompt: Description
+    min_length: 3
+  - name: use_docker
+    prompt: Add Docker?
+    type: boolean
+    default: false
+  - name: kind
+    prompt: Kind
+    type: select
+    options: [Binary, Library]
+  - name: features
+    prompt: Features
+    type: multiselect
+    options: [cli, web, db]
+  - name: port
+    prompt: Port
+    type: integer
+    default: 8080
+    min: 1
+    if: '"web" in features'
+steps:
+  - name: Copy files
+    copy:
+      from: service
+      to: .
+  - name: Dockerfile
+    if: use_docker
+    run: echo "EXPOSE {port}" > Dockerfile
+  - name: Library marker
+    if: kind == "Library"
+    run: touch lib.marker
+"#;
+
+pub const SERVICE_README: &str = "# {{ project_name_title }}\n\n{{ description }}\n{% for feature in features %}\n- {{ feature }}\n{% endfor %}";
+
+/// A temporary home directory with a templates directory
+pub struct TestHome {
+    dir: TempDir,
+}
+
+impl TestHome {
+    pub fn new() -> Self {
+        let home = TestHome { dir: TempDir::new().unwrap() };
+        fs::create_dir_all(home.templates()).unwrap();
+        fs::create_dir_all(home.projects()).unwrap();
+        home
+    }
+
+    /// A home with the `service` template installed
+    pub fn with_service_template() -> Self {
+        let home = TestHome::new();
+        home.template("service", SERVICE_TEMPLATE);
+        home.template_file("service/README.md", SERVICE_README);
+        home.template_file("service/src/{project_name_snake}.rs", "pub const NAME: &str = \"{{ project_name }}\";\n");
+        home
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    pub fn templates(&self) -> PathBuf {
+        self.path().join(".config/newnew/templates")
+    }
+
+    /// Where tests create projects
+    pub fn projects(&self) -> PathBuf {
+        self.path().join("projects")
+    }
+
+    pub fn template(&self, name: &str, yaml: &str) {
+        fs::write(self.templates().join(format!("{}.yml", name)), yaml).unwrap();
+    }
+
+    pub fn template_file(&self, relative: &str, content: &str) {
+        let path = self.templates().join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    /// Point `$HOME` at this directory until the guard is dropped
+    pub fn activate(&self) -> MutexGuard<'static, ()> {
+        let guard = HOME_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        env::set_var("HOME", self.path());
+        for name in OVERRIDE_VARS {
+            env::remove_var(name);
+        }
+        guard
+    }
+}
+
+pub fn read(path: impl AsRef<Path>) -> String {
+    fs::read_to_string(path.as_ref())
+        .unwrap_or_else(|e| panic!("Failed to read '{}': {}", path.as_ref().display(), e))
+}
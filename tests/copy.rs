@@ -0,0 +1,163 @@
+mod common;
+
+use common::{TestHome, read};
+use newnew::{Generator, SilentReporter, Value, load_templates};
+use std::collections::HashMap;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+
+/// Generate `name` from the template `template` with its defaults
+fn generate(home: &TestHome, template: &str, name: &str) -> PathBuf {
+    let template = load_templates(None, &mut SilentReporter).unwrap().remove(template).unwrap();
+    Generator::new(template, HashMap::new(), home.projects().join(name)).generate().unwrap()
+}
+
+#[test]
+fn directories_are_filtered_by_include_and_exclude() {
+    let home = TestHome::new();
+    home.template("python", r#"
+name: Python
+description: Package skeleton
+emoji: "🐍"
+steps:
+  - name: Copy skeleton
+    copy:
+      from: python
+      to: .
+      include: ["src/**", "*.toml"]
+      exclude: ["**/__pycache__/**", "*.pyc"]
+"#);
+    home.template_file("python/pyproject.toml", "[project]\nname = \"{{ project_name }}\"\n");
+    home.template_file("python/NOTES.md", "Not included\n");
+    home.template_file("python/src/{project_name_snake}/__init__.py", "NAME = \"{project_name}\"\n");
+    home.template_file("python/src/{project_name_snake}/cache.pyc", "excluded");
+    home.template_file("python/src/{project_name_snake}/__pycache__/module.txt", "excluded");
+    let _home = home.activate();
+
+    let project = generate(&home, "python", "data-tools");
+
+    assert_eq!(read(project.join("pyproject.toml")), "[project]\nname = \"data-tools\"\n");
+    assert_eq!(read(project.join("src/data_tools/__init__.py")), "NAME = \"data-tools\"\n");
+    assert!(!project.join("NOTES.md").exists());
+    assert!(!project.join("src/data_tools/cache.pyc").exists());
+    assert!(!project.join("src/data_tools/__pycache__").exists());
+    assert!(!project.join("src/{project_name_snake}").exists());
+}
+
+#[test]
+fn destinations_and_directory_names_are_rendered() {
+    let home = TestHome::new();
+    home.template("nested", r#"
+name: Nested
+description: Renamed directories
+emoji: "📁"
+steps:
+  - name: Copy package
+    copy:
+      from: nested/package
+      to: "{{ project_name_kebab }}-pkg"
+  - name: Copy single file
+    copy:
+      from: nested/init.py
+      to: "src/{project_name}/__init__.py"
+"#);
+    home.template_file("nested/package/{project_name}/{{ project_name_pascal }}.txt", "{project_name_pascal}\n");
+    home.template_file("nested/init.py", "print('{{ project_name }}')\n");
+    let _home = home.activate();
+
+    let project = generate(&home, "nested", "my_app");
+
+    assert_eq!(read(project.join("my-app-pkg/my_app/MyApp.txt")), "MyApp\n");
+    assert_eq!(read(project.join("src/my_app/__init__.py")), "print('my_app')\n");
+}
+
+#[test]
+fn binary_files_and_permissions_are_kept() {
+    let home = TestHome::new();
+    home.template("java", r#"
+name: Java
+description: Gradle project
+emoji: "☕"
+steps:
+  - name: Copy wrapper
+    copy:
+      from: java
+      to: .
+      no_render: ["*.properties"]
+  - name: Copy scripts
+    copy:
+      from: scripts
+      to: scripts
+      raw: true
+"#);
+    let png = b"\x89PNG\r\n\x1a\n\0\0\x01{{ project_name }}".to_vec();
+    let latin1 = b"caf\xe9 {{ project_name }}\n".to_vec();
+    let files = [
+        ("java/logo.png", png.clone()),
+        ("java/latin1.txt", latin1.clone()),
+        ("java/gradlew", b"#!/bin/sh\necho {{ project_name }}\n".to_vec()),
+        ("java/gradle/wrapper.properties", b"name={{ project_name }}\n".to_vec()),
+        ("scripts/count.sh", b"echo ${#items[@]} {{ project_name }}\n".to_vec()),
+    ];
+    for (relative, content) in &files {
+        let path = home.templates().join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    let gradlew = home.templates().join("java/gradlew");
+    fs::set_permissions(&gradlew, fs::Permissions::from_mode(0o755)).unwrap();
+    let _home = home.activate();
+
+    let project = generate(&home, "java", "app");
+
+    // Binary and non-UTF-8 files are copied byte for byte
+    assert_eq!(fs::read(project.join("logo.png")).unwrap(), png);
+    assert_eq!(fs::read(project.join("latin1.txt")).unwrap(), latin1);
+    // Text files are rendered and keep their mode
+    assert_eq!(read(project.join("gradlew")), "#!/bin/sh\necho app\n");
+    let mode = fs::metadata(project.join("gradlew")).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o755);
+    // no_render and raw files are copied as they are
+    assert_eq!(read(project.join("gradle/wrapper.properties")), "name={{ project_name }}\n");
+    assert_eq!(read(project.join("scripts/count.sh")), "echo ${#items[@]} {{ project_name }}\n");
+}
+
+#[test]
+fn destinations_stay_inside_the_project() {
+    let home = TestHome::new();
+    let template = |to: &str| format!(r#"
+name: Module
+description: Copies into a named module
+emoji: "🧩"
+variables:
+  - name: module
+    prompt: Module
+steps:
+  - name: Copy module
+    copy:
+      from: module
+      to: "{}"
+"#, to);
+    home.template("into", &template("src/{module}"));
+    home.template("named", &template("src"));
+    home.template_file("module/a.txt", "a\n");
+    home.template_file("module/{module}/b.txt", "b\n");
+    let _home = home.activate();
+
+    // In the destination, in a directory name, and as an absolute path
+    let cases = [
+        ("into", "into", "../../../escaped"),
+        ("named", "named", "../../../escaped"),
+        ("named", "absolute", "/escaped"),
+    ];
+    for (template, name, module) in cases {
+        let variables = HashMap::from([("module".to_string(), Value::from(module))]);
+        let template = load_templates(None, &mut SilentReporter).unwrap().remove(template).unwrap();
+        let error = Generator::new(template, variables, home.projects().join(name)).generate().unwrap_err();
+
+        assert!(error.to_string().contains("must be a relative path inside the project"), "{}", error);
+        assert!(!home.projects().join(name).exists());
+    }
+    assert!(!home.path().join("escaped").exists());
+}
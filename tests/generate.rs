@@ -0,0 +1,589 @@
+mod common;
+
+use common::{TestHome, read};
+use newnew::{
+    Generator, ProjectOptions, Prompter, Reporter, ScriptedPrompter, SilentReporter, Value, create_project,
+    load_templates, prompt_project_config,
+};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+
+fn service_generator(home: &TestHome, name: &str, variables: HashMap<String, Value>) -> Generator {
+    let template = load_templates(None, &mut SilentReporter).unwrap().remove("service").unwrap();
+    Generator::new(template, variables, home.projects().join(name))
+}
+
+#[test]
+fn scripted_answers_generate_project() {
+    let home = TestHome::with_service_template();
+    let _home = home.activate();
+
+    let mut prompter = ScriptedPrompter::new(["Handles billing", "yes", "Library", "cli,web", "9090"]);
+    let project = service_generator(&home, "billing-service", HashMap::new())
+        .generate_with(&mut prompter)
+        .unwrap();
+
+    assert_eq!(prompter.remaining(), 0);
+    let readme = read(project.join("README.md"));
+    assert!(readme.starts_with("# Billing Service\n\nHandles billing\n"));
+    assert!(readme.contains("- cli") && readme.contains("- web") && !readme.contains("- db"));
+    assert_eq!(read(project.join("src/billing_service.rs")), "pub const NAME: &str = \"billing-service\";\n");
+    assert_eq!(read(project.join("Dockerfile")).trim(), "EXPOSE 9090");
+    assert!(project.join("lib.marker").exists());
+
+    let answers = read(project.join(".newnew-answers.yml"));
+    assert!(answers.contains("template: service"));
+    assert!(answers.contains("port: 9090"));
+    assert!(answers.contains("use_docker: true"));
+}
+
+#[test]
+fn invalid_answers_are_asked_again() {
+    let home = TestHome::with_service_template();
+    let _home = home.activate();
+
+    // "ab" is too short, so the description is asked for twice. Empty
+    // answers accept the defaults, and the port is skipped without "web".
+    let mut prompter = ScriptedPrompter::new(["ab", "A longer description", "", "Binary", ""]);
+    let project = service_generator(&home, "retry", HashMap::new())
+        .generate_with(&mut prompter)
+        .unwrap();
+
+    assert_eq!(prompter.remaining(), 0);
+    assert!(read(project.join("README.md")).contains("A longer description"));
+    assert!(!project.join("Dockerfile").exists());
+    assert!(!project.join("lib.marker").exists());
+    assert!(!read(project.join(".newnew-answers.yml")).contains("port:"));
+}
+
+#[test]
+fn running_out_of_answers_fails() {
+    let home = TestHome::with_service_template();
+    let _home = home.activate();
+
+    let mut prompter = ScriptedPrompter::new(["Only one answer"]);
+    let error = service_generator(&home, "unfinished", HashMap::new())
+        .generate_with(&mut prompter)
+        .unwrap_err();
+
+    assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    assert!(!home.projects().join("unfinished").exists());
+}
+
+#[test]
+fn answers_that_dont_fit_the_question_fail() {
+    let home = TestHome::with_service_template();
+    let _home = home.activate();
+
+    let mut prompter = ScriptedPrompter::new(["Fine", "maybe"]);
+    let error = service_generator(&home, "unsure", HashMap::new())
+        .generate_with(&mut prompter)
+        .unwrap_err();
+
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    assert!(error.to_string().contains("maybe"));
+}
+
+#[test]
+fn generate_uses_defaults_and_reports_missing_values() {
+    let home = TestHome::with_service_template();
+    let _home = home.activate();
+
+    let error = service_generator(&home, "incomplete", HashMap::new()).generate().unwrap_err();
+    assert!(error.to_string().contains("description"), "{}", error);
+
+    let variables = HashMap::from([("description".to_string(), Value::from("Defaults everywhere"))]);
+    let project = service_generator(&home, "defaults", variables).generate().unwrap();
+
+    let answers = read(project.join(".newnew-answers.yml"));
+    assert!(answers.contains("kind: Binary"));
+    assert!(answers.contains("use_docker: false"));
+    assert!(!project.join("lib.marker").exists());
+}
+
+#[test]
+fn supplied_values_are_validated() {
+    let home = TestHome::with_service_template();
+    let _home = home.activate();
+
+    let variables = HashMap::from([
+        ("description".to_string(), Value::from("Valid")),
+        ("kind".to_string(), Value::from("Plugin")),
+    ]);
+    let error = service_generator(&home, "invalid", variables).generate().unwrap_err();
+
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    assert!(error.to_string().contains("Plugin"));
+    assert!(!home.projects().join("invalid").exists());
+
+    let variables = HashMap::from([("description".to_string(), Value::from("Valid"))]);
+    let error = service_generator(&home, "my service", variables).generate().unwrap_err();
+    assert!(error.to_string().contains("whitespace"), "{}", error);
+}
+
+#[test]
+fn computed_variables() {
+    let home = TestHome::new();
+    home.template("computed", r#"
+name: Computed
+description: Values from expressions and commands
+emoji: "🧮"
+variables:
+  - name: module
+    value: "{{ project_name | snake_case }}_core"
+  - name: author
+    command: echo Ada
+  - name: year
+    type: integer
+    command: echo 2024
+  - name: branch
+    prompt: Branch
+    command: exit 1
+    default: main
+steps:
+  - name: Write
+    run: echo "{module} {author} {year} {branch}" > out.txt
+"#);
+    home.template("fallback", r#"
+name: Fallback
+description: Command without a prompt fails
+emoji: "🪂"
+variables:
+  - name: author
+    command: exit 2
+    default: Nobody
+steps:
+  - name: Write
+    run: echo "{author}" > author.txt
+"#);
+    home.template("failing", r#"
+name: Failing
+description: Command without a prompt or default fails
+emoji: "💥"
+variables:
+  - name: author
+    command: exit 2
+"#);
+    let _home = home.activate();
+
+    let mut templates = load_templates(None, &mut SilentReporter).unwrap();
+    let template = templates.remove("computed").unwrap();
+    let project = Generator::new(template, HashMap::new(), home.projects().join("billing-api"))
+        .generate()
+        .unwrap();
+    // The failing command of `branch` falls back to its default
+    assert_eq!(read(project.join("out.txt")), "billing_api_core Ada 2024 main\n");
+
+    // So does the one of `author`, which isn't prompted for
+    let template = templates.remove("fallback").unwrap();
+    let project = Generator::new(template, HashMap::new(), home.projects().join("fallback"))
+        .generate()
+        .unwrap();
+    assert_eq!(read(project.join("author.txt")), "Nobody\n");
+
+    let template = templates.remove("failing").unwrap();
+    let error = Generator::new(template, HashMap::new(), home.projects().join("failing"))
+        .generate()
+        .unwrap_err();
+    assert!(error.to_string().contains("Variable 'author'"), "{}", error);
+    assert!(!home.projects().join("failing").exists());
+}
+
+#[test]
+fn step_conditions_skip_steps() {
+    let home = TestHome::new();
+    home.template("conditional", r#"
+name: Conditional
+description: Steps that depend on answers
+emoji: "🔀"
+variables:
+  - name: use_docker
+    type: boolean
+    default: false
+steps:
+  - name: Docker
+    if: use_docker
+    run: touch docker.txt
+  - name: Docker, old spelling
+    if_: use_docker
+    run: touch docker-old.txt
+  - name: No Docker
+    if-not: use_docker
+    run: touch plain.txt
+"#);
+    let _home = home.activate();
+
+    let template = load_templates(None, &mut SilentReporter).unwrap().remove("conditional").unwrap();
+    let project = Generator::new(template, HashMap::new(), home.projects().join("conditional"))
+        .generate()
+        .unwrap();
+
+    assert!(!project.join("docker.txt").exists());
+    assert!(!project.join("docker-old.txt").exists());
+    assert!(project.join("plain.txt").exists());
+}
+
+#[test]
+fn failing_step_removes_the_project() {
+    let home = TestHome::new();
+    home.template("broken", r#"
+name: Broken
+description: Fails halfway
+emoji: "💥"
+steps:
+  - name: Write a file
+    run: echo partial > partial.txt
+  - name: Fail
+    run: exit 3
+"#);
+    let _home = home.activate();
+
+    let template = load_templates(None, &mut SilentReporter).unwrap().remove("broken").unwrap();
+    let error = Generator::new(template, HashMap::new(), home.projects().join("broken"))
+        .generate()
+        .unwrap_err();
+
+    assert!(error.to_string().contains("exited with code 3"), "{}", error);
+    assert!(!home.projects().join("broken").exists());
+}
+
+#[test]
+fn failing_commands_are_retried() {
+    let home = TestHome::new();
+    home.template("flaky", r#"
+name: Flaky
+description: Fails once
+emoji: "🎲"
+steps:
+  - name: Flaky
+    retries: 1
+    run: echo attempt >> attempts.txt && test -f marker || { touch marker; exit 1; }
+  - name: Always failing
+    retries: 1
+    run: echo attempt >> failures.txt && exit 4
+"#);
+    let _home = home.activate();
+
+    let template = load_templates(None, &mut SilentReporter).unwrap().remove("flaky").unwrap();
+    let mut generator = Generator::new(template, HashMap::new(), home.projects().join("flaky"));
+    generator.keep_on_failure = true;
+    let error = generator.generate().unwrap_err();
+
+    let project = home.projects().join("flaky");
+    assert_eq!(read(project.join("attempts.txt")), "attempt\nattempt\n");
+    // Retries run out: the original attempt plus one retry
+    assert_eq!(read(project.join("failures.txt")), "attempt\nattempt\n");
+    assert!(error.to_string().contains("Step 'Always failing' failed"), "{}", error);
+}
+
+#[test]
+fn continue_on_error_runs_the_remaining_commands() {
+    let home = TestHome::new();
+    home.template("optional", r#"
+name: Optional
+description: Allowed to fail
+emoji: "🤷"
+steps:
+  - name: Optional tools
+    continue_on_error: true
+    run: |
+      exit 1
+      touch after-failure.txt
+  - name: Next step
+    run: touch next-step.txt
+"#);
+    let _home = home.activate();
+
+    let template = load_templates(None, &mut SilentReporter).unwrap().remove("optional").unwrap();
+    let project = Generator::new(template, HashMap::new(), home.projects().join("optional"))
+        .generate()
+        .unwrap();
+
+    assert!(project.join("after-failure.txt").exists());
+    assert!(project.join("next-step.txt").exists());
+}
+
+#[test]
+fn prompts_for_template_and_project_name() {
+    let home = TestHome::with_service_template();
+    home.template("other", "name: Other\ndescription: Another template\nemoji: \"📦\"\nsteps: []\n");
+    let _home = home.activate();
+
+    let options = ProjectOptions {
+        target_dir: Some(home.projects().to_string_lossy().into_owned()),
+        ..ProjectOptions::default()
+    };
+    let mut prompter = ScriptedPrompter::new(["service", "../escape", "picked", "Picked from a list", "no", "Library", "db"]);
+    let config = prompt_project_config(options, &mut prompter, &mut SilentReporter).unwrap();
+
+    assert_eq!(prompter.remaining(), 0);
+    assert_eq!(config.template_name, "service");
+    assert_eq!(config.name, "picked");
+    assert_eq!(config.variables["features"], Value::List(vec!["db".to_string()]));
+
+    let project = create_project(&config, false, &mut SilentReporter).unwrap();
+    assert_eq!(project, home.projects().join("picked"));
+    assert!(project.join("lib.marker").exists());
+}
+
+#[test]
+fn scripted_template_choice_matches_ids_only() {
+    let home = TestHome::with_service_template();
+    home.template("other", "name: Other\ndescription: Like service but smaller\nemoji: \"📦\"\nsteps: []\n");
+    let _home = home.activate();
+
+    let options = || ProjectOptions {
+        target_dir: Some(home.projects().to_string_lossy().into_owned()),
+        name: Some("picked".to_string()),
+        ..ProjectOptions::default()
+    };
+
+    // "other" comes first and mentions "service" in its description
+    let mut prompter = ScriptedPrompter::new(["service", "Picked", "no", "Library", "db"]);
+    let config = prompt_project_config(options(), &mut prompter, &mut SilentReporter).unwrap();
+    assert_eq!(config.template_name, "service");
+
+    let mut prompter = ScriptedPrompter::new(["smaller"]);
+    let error = prompt_project_config(options(), &mut prompter, &mut SilentReporter).unwrap_err();
+    assert!(error.to_string().contains("Scripted answer 'smaller'"), "{}", error);
+}
+
+/// Records the options it is asked to choose from and picks the first
+struct RecordingPrompter {
+    options: Vec<String>,
+}
+
+impl Prompter for RecordingPrompter {
+    fn input(&mut self, _prompt: &str) -> io::Result<String> {
+        Ok("recorded".to_string())
+    }
+
+    fn select(&mut self, _prompt: &str, options: &[String]) -> io::Result<usize> {
+        self.options = options.to_vec();
+        Ok(0)
+    }
+
+    fn confirm(&mut self, _prompt: &str, default: bool) -> io::Result<bool> {
+        Ok(default)
+    }
+
+    fn multiselect(&mut self, _prompt: &str, _options: &[String], defaults: &[String]) -> io::Result<Vec<String>> {
+        Ok(defaults.to_vec())
+    }
+
+    fn password(&mut self, _prompt: &str) -> io::Result<String> {
+        Ok(String::new())
+    }
+}
+
+#[test]
+fn template_picker_shows_roots() {
+    let home = TestHome::new();
+    home.template("plain", "name: Plain\ndescription: From home\nemoji: \"🏡\"\nsteps: []\n");
+    let config_dir = home.path().join("shared");
+    fs::create_dir_all(&config_dir).unwrap();
+    fs::write(config_dir.join("shared.yml"), "name: Shared\ndescription: From config\nemoji: \"🤝\"\nsteps: []\n").unwrap();
+    let config = home.path().join(".config/newnew/newnew.toml");
+    fs::write(&config, format!("[settings]\ntemplate_dirs = [{:?}]\n", config_dir)).unwrap();
+    let _home = home.activate();
+
+    let options = ProjectOptions {
+        target_dir: Some(home.projects().to_string_lossy().into_owned()),
+        ..ProjectOptions::default()
+    };
+    let mut prompter = RecordingPrompter { options: Vec::new() };
+    prompt_project_config(options, &mut prompter, &mut SilentReporter).unwrap();
+
+    assert_eq!(prompter.options, vec!["🏡 plain From home (user)", "🤝 shared From config (config)"]);
+}
+
+#[test]
+fn config_file_option_replaces_the_home_config() {
+    let home = TestHome::new();
+    home.template("plain", "name: Plain\ndescription: From home\nemoji: \"🏡\"\nsteps: []\n");
+    let team_dir = home.path().join("team");
+    fs::create_dir_all(team_dir.join("templates")).unwrap();
+    fs::write(team_dir.join("templates/team.yml"), "name: Team\ndescription: Shared\nemoji: \"👥\"\nsteps: []\n").unwrap();
+    // Relative to the config file, not the current directory
+    let config = team_dir.join("team.toml");
+    fs::write(&config, "[settings]\ntemplate_dirs = [\"templates\"]\n").unwrap();
+    let _home = home.activate();
+
+    let options = ProjectOptions {
+        target_dir: Some(home.projects().to_string_lossy().into_owned()),
+        config_file: Some(config),
+        ..ProjectOptions::default()
+    };
+    let mut prompter = RecordingPrompter { options: Vec::new() };
+    prompt_project_config(options, &mut prompter, &mut SilentReporter).unwrap();
+
+    assert_eq!(prompter.options, vec!["🏡 plain From home (user)", "👥 team Shared (config)"]);
+    // The home config isn't written when another one is used
+    assert!(!home.path().join(".config/newnew/newnew.toml").exists());
+}
+
+#[test]
+fn bundle_hash_covers_its_files() {
+    let home = TestHome::new();
+    home.template_file("api/template.yml", "name: Api\ndescription: x\nemoji: \"x\"\nsteps: []\n");
+    home.template_file("api/files/README.md", "# v1\n");
+    let _home = home.activate();
+    let hash = || load_templates(None, &mut SilentReporter).unwrap()["api"].hash.clone();
+
+    let original = hash();
+    assert_eq!(hash(), original);
+
+    home.template_file("api/files/README.md", "# v2\n");
+    let edited = hash();
+    assert_ne!(edited, original);
+
+    home.template_file("api/files/CHANGELOG.md", "");
+    assert_ne!(hash(), edited);
+}
+
+#[test]
+fn bundles_copy_files_relative_to_themselves() {
+    let home = TestHome::new();
+    let copy_template = |from: &str| format!(
+        "name: Bundle\ndescription: Self-contained\nemoji: \"📦\"\nsteps:\n  - name: Copy\n    copy:\n      from: {}\n      to: .\n",
+        from
+    );
+    home.template_file("api/template.yml", &copy_template("files"));
+    home.template_file("api/files/README.md", "# {{ project_name }} from the bundle\n");
+    // A flat template in the same root still works, with paths relative to
+    // the root
+    home.template("legacy", &copy_template("legacy"));
+    home.template_file("legacy/README.md", "# {{ project_name }} from the root\n");
+    home.template_file("escape/template.yml", &copy_template("../legacy"));
+    let _home = home.activate();
+
+    let mut templates = load_templates(None, &mut SilentReporter).unwrap();
+    let mut generate = |name: &str| {
+        let template = templates.remove(name).unwrap();
+        Generator::new(template, HashMap::new(), home.projects().join(name)).generate()
+    };
+
+    let project = generate("api").unwrap();
+    assert_eq!(read(project.join("README.md")), "# api from the bundle\n");
+
+    let project = generate("legacy").unwrap();
+    assert_eq!(read(project.join("README.md")), "# legacy from the root\n");
+
+    let error = generate("escape").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    assert!(!home.projects().join("escape").exists());
+}
+
+const BASE_TEMPLATE: &str = r#"
+name: Base
+description: Shared setup
+emoji: "🧱"
+variables:
+  - name: license
+    prompt: License
+    default: MIT
+  - name: author
+    prompt: Author
+    default: Nobody
+steps:
+  - name: License
+    copy:
+      from: files/LICENSE
+      to: LICENSE
+  - name: Readme
+    run: echo "base" > README.md
+  - name: Changelog
+    run: touch CHANGELOG.md
+"#;
+
+const CHILD_TEMPLATE: &str = r#"
+name: Child
+description: Builds on base
+emoji: "🌱"
+extends: base
+variables:
+  - name: license
+    prompt: License
+    default: Apache-2.0
+  - name: port
+    prompt: Port
+    type: integer
+    default: 8080
+steps:
+  - name: Readme
+    run: echo "child {port}" > README.md
+  - name: Changelog
+    remove: true
+  - name: Prepare
+    before: License
+    run: echo prepare > order.txt
+  - name: First
+    after: License
+    run: echo first >> order.txt
+  - name: Second
+    after: License
+    run: echo second >> order.txt
+"#;
+
+#[test]
+fn templates_extend_other_templates() {
+    let home = TestHome::new();
+    home.template_file("base/template.yml", BASE_TEMPLATE);
+    home.template_file("base/files/LICENSE", "{{ license }} by {{ author }}\n");
+    home.template_file("child/template.yml", CHILD_TEMPLATE);
+    let _home = home.activate();
+
+    let mut templates = load_templates(None, &mut SilentReporter).unwrap();
+    let child = templates.remove("child").unwrap();
+
+    let variables: Vec<&str> = child.variables.iter().map(|var| var.name.as_str()).collect();
+    assert_eq!(variables, ["license", "author", "port"]);
+    let steps: Vec<&str> = child.steps.iter().map(|step| step.name.as_str()).collect();
+    assert_eq!(steps, ["Prepare", "License", "First", "Second", "Readme"]);
+
+    let project = Generator::new(child, HashMap::new(), home.projects().join("child")).generate().unwrap();
+    // The inherited copy step still copies from the base bundle
+    assert_eq!(read(project.join("LICENSE")), "Apache-2.0 by Nobody\n");
+    assert_eq!(read(project.join("README.md")), "child 8080\n");
+    assert_eq!(read(project.join("order.txt")), "prepare\nfirst\nsecond\n");
+    assert!(!project.join("CHANGELOG.md").exists());
+}
+
+#[test]
+fn broken_inheritance_skips_the_template() {
+    let home = TestHome::new();
+    home.template_file("base/template.yml", BASE_TEMPLATE);
+    home.template_file("base/files/LICENSE", "{{ license }}\n");
+    home.template("orphan", "name: Orphan\ndescription: x\nemoji: \"x\"\nextends: missing\n");
+    home.template("ping", "name: Ping\ndescription: x\nemoji: \"x\"\nextends: pong\n");
+    home.template("pong", "name: Pong\ndescription: x\nemoji: \"x\"\nextends: ping\n");
+    home.template("misplaced", "name: M\ndescription: x\nemoji: \"x\"\nextends: base\nsteps:\n  - name: X\n    after: Deploy\n    run: \"true\"\n");
+    let _home = home.activate();
+
+    let mut reporter = RecordingReporter::default();
+    let templates = load_templates(None, &mut reporter).unwrap();
+    let mut names: Vec<&str> = templates.keys().map(String::as_str).collect();
+    names.sort();
+    assert_eq!(names, ["base"]);
+    // Each skipped template is reported, and nothing else
+    assert_eq!(reporter.warnings.len(), 4, "{:?}", reporter.warnings);
+    for name in ["orphan", "ping", "pong", "misplaced"] {
+        let quoted = format!("'{}'", name);
+        assert!(reporter.warnings.iter().any(|warning| warning.contains(&quoted)), "{:?}", reporter.warnings);
+    }
+}
+
+/// Keeps the warnings it is given, so tests can check them
+#[derive(Default)]
+struct RecordingReporter {
+    warnings: Vec<String>,
+}
+
+impl Reporter for RecordingReporter {
+    fn progress(&mut self, _message: &str) {}
+
+    fn warning(&mut self, message: &str) {
+        self.warnings.push(message.to_string());
+    }
+}